@@ -7,6 +7,7 @@ import android.location.LocationListener;
 import android.location.LocationManager;
 import android.os.Handler;
 import android.os.Looper;
+import android.os.SystemClock;
 import android.util.Log;
 
 
@@ -20,7 +21,15 @@ public class LocationHelper {
             @Override
             public void onLocationChanged(Location location) {
                 // Call a static native method without nativePtr
-                onLocationUpdate(location.getLatitude(), location.getLongitude(), location.getAccuracy(), location.getElapsedRealtimeNanos() / 1_000_000_000.0);
+                // optional values are passed as NaN when the provider does not report them
+                onLocationUpdate(location.getLatitude(), location.getLongitude(), location.getAccuracy(),
+                        location.getElapsedRealtimeNanos() / 1_000_000_000.0,
+                        location.getTime() != 0 ? location.getTime() / 1000.0 : Double.NaN,
+                        location.hasAltitude() ? location.getAltitude() : Double.NaN,
+                        location.hasVerticalAccuracy() ? location.getVerticalAccuracyMeters() : Double.NaN,
+                        location.hasSpeed() ? location.getSpeed() : Double.NaN,
+                        location.hasSpeedAccuracy() ? location.getSpeedAccuracyMetersPerSecond() : Double.NaN,
+                        location.hasBearing() ? location.getBearing() : Double.NaN);
 //                Log.i("GPS", "Location info: ACC: " + location.getAccuracy() + ", altitude: " +
 //                        location.getAltitude() + " +-" + location.getVerticalAccuracyMeters() +
 //                        "\nTime: " + location.getElapsedRealtimeNanos() / 1000000000 +
@@ -31,14 +40,14 @@ public class LocationHelper {
             @Override
             public void onProviderEnabled(String provider) {
                 if (provider.equals("gps")) {
-                    LocationHelper.onProviderEnabled();
+                    LocationHelper.onProviderEnabled(SystemClock.elapsedRealtimeNanos() / 1_000_000_000.0);
                 }
             }
 
             @Override
             public void onProviderDisabled(String provider) {
                 if (provider.equals("gps")) {
-                    LocationHelper.onProviderDisabled();
+                    LocationHelper.onProviderDisabled(SystemClock.elapsedRealtimeNanos() / 1_000_000_000.0);
                 }
             }
 
@@ -72,11 +81,14 @@ public class LocationHelper {
     }
 
     // Modified to not use nativePtr
-    private native void onLocationUpdate(double latitude, double longitude, double acc, double timestamp);
+    // timestamp is monotonic elapsedRealtime, utcTime is the UTC time of the fix, both in seconds
+    private native void onLocationUpdate(double latitude, double longitude, double acc, double timestamp, double utcTime,
+                                         double altitude, double verticalAcc, double speed, double speedAcc, double bearing);
 
     public native void onPermissionDenied();
     public native void onPermissionGranted();
 
-    public static native void onProviderEnabled();
-    public static native void onProviderDisabled();
+    // timestamp is SystemClock.elapsedRealtimeNanos() in seconds, same timebase as location updates
+    public static native void onProviderEnabled(double timestamp);
+    public static native void onProviderDisabled(double timestamp);
 }
\ No newline at end of file
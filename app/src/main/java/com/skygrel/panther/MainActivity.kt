@@ -4,7 +4,11 @@ import android.Manifest
 import android.app.NativeActivity
 import android.content.Context
 import android.content.pm.PackageManager
+import android.os.Build
 import android.os.Bundle
+import android.os.VibrationEffect
+import android.os.Vibrator
+import android.speech.tts.TextToSpeech
 import android.util.Log
 import androidx.core.app.ActivityCompat
 import androidx.core.content.ContextCompat
@@ -15,12 +19,42 @@ class MainActivity : NativeActivity(), ActivityCompat.OnRequestPermissionsResult
 
     var locationHelper: LocationHelper? = null
 
+    private var textToSpeech: TextToSpeech? = null
+    private var speechReady = false
+
     override fun onCreate(savedInstanceState: Bundle?) {
         super.onCreate(savedInstanceState)
 
 
 
         this.locationHelper = LocationHelper(this)
+        this.textToSpeech = TextToSpeech(this) { status ->
+            speechReady = status == TextToSpeech.SUCCESS
+            Log.i("Cues", "Text to speech ready: $speechReady")
+        }
+    }
+
+    override fun onDestroy() {
+        textToSpeech?.shutdown()
+        super.onDestroy()
+    }
+
+    // Called from native code to speak a coaching cue
+    private fun speak(text: String) {
+        if (speechReady) {
+            textToSpeech?.speak(text, TextToSpeech.QUEUE_ADD, null, "cue")
+        }
+    }
+
+    // Called from native code to buzz on a coaching cue
+    @Suppress("DEPRECATION")
+    private fun vibrate(millis: Long) {
+        val vibrator = getSystemService(Context.VIBRATOR_SERVICE) as? Vibrator ?: return
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            vibrator.vibrate(VibrationEffect.createOneShot(millis, VibrationEffect.DEFAULT_AMPLITUDE))
+        } else {
+            vibrator.vibrate(millis)
+        }
     }
 
     private fun checkAndRequestPermissions() {
@@ -223,9 +223,14 @@ impl App {
         println!("Android window removed");
 
         // Destroy the GL Surface and un-current the GL Context before ndk-glue releases
-        // the window back to the system.
-        let gl_context = self.gl_context.take().unwrap();
-        gl_context.make_not_current().unwrap();
+        // the window back to the system. The context itself is kept, so textures and
+        // screens stay valid and are bound to the new surface on resume.
+        self.gl_surface = None;
+        if let Some(gl_context) = self.gl_context.take() {
+            let gl_context = gl_context.make_not_current().unwrap();
+            self.gl_context = Some(gl_context.treat_as_possibly_current());
+        }
+        self.app_state.suspend();
     }
 
     /// can potentially call exit
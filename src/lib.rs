@@ -14,9 +14,13 @@ use winit::platform::android::activity::AndroidApp;
 use winit::window::WindowId;
 use crate::app::App;
 use crate::render::ANDROID_DATA_PATH;
+use crate::tracking::RECORDER;
+use crate::tracking::cues::android::{SpeechSink, VibrationSink};
+use crate::tracking::cues::banner::BannerSink;
 
 pub mod app;
 pub mod render;
+pub mod tracking;
 
 pub static JNI_ENV: Mutex<Option<usize>> = Mutex::new(None);
 pub static ACTIVITY_OBJ: Mutex<Option<JObject>> = Mutex::new(None);
@@ -71,6 +75,20 @@ fn set_max_framerate(android_app: &AndroidApp) {
     ACTIVITY_OBJ.lock().replace(activity);
 }
 
+/// Outputs of the coaching cues: speech, vibration and the on-screen banner
+fn register_cue_sinks(android_app: &AndroidApp) {
+    let vm = || unsafe { JavaVM::from_raw(android_app.vm_as_ptr() as _) }.unwrap();
+    let jvm = vm();
+    let env = jvm.get_env().unwrap();
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as jobject) };
+    let activity = env.new_global_ref(activity).unwrap();
+
+    let mut recorder = RECORDER.lock();
+    recorder.add_cue_sink(Box::new(SpeechSink::new(vm(), activity.clone())));
+    recorder.add_cue_sink(Box::new(VibrationSink::new(vm(), activity)));
+    recorder.add_cue_sink(Box::new(BannerSink));
+}
+
 struct WinitApp {
     app: Option<App>,
     exit_request: Arc<AtomicBool>,
@@ -171,6 +189,7 @@ fn android_main(app: AndroidApp) {
     );
 
     set_max_framerate(&app);
+    register_cue_sinks(&app);
 
     let event_loop = EventLoopBuilder::default().with_android_app(app).build().unwrap();
     run(event_loop);
@@ -8,9 +8,12 @@ use winit::dpi::{PhysicalPosition, PhysicalSize};
 use crate::render::fonts::load_fonts;
 use crate::render::gl::UNPACK_ALIGNMENT;
 use crate::render::images::load_images;
+use crate::render::screens::active_training::ActiveTrainingScreen;
 use crate::render::screens::main::MainScreen;
+use crate::render::screens::paused_screen::PausedScreen;
 use crate::render::screens::{ScreenManagementCmd, ScreenTrait};
 use crate::render::screens::records::{Records, RECORDS_LIST};
+use crate::tracking::{RECORDER, RecordingState, TRACKING_CONFIG, TrackingConfig, USER_PROFILE, UserProfile};
 
 pub mod utils;
 pub mod objects;
@@ -115,6 +118,10 @@ impl AppState {
             warn!("File open failed! Creating empty records object...");
         }
 
+        *TRACKING_CONFIG.lock() = TrackingConfig::load(ANDROID_DATA_PATH);
+        *USER_PROFILE.lock() = UserProfile::load(ANDROID_DATA_PATH);
+        RECORDER.lock().set_journal_dir(ANDROID_DATA_PATH);
+
         AppState {
             screens: Vec::new(),
             exit_request,
@@ -162,12 +169,33 @@ impl AppState {
         SURFACE_WIDTH.store(dims.width, Ordering::Relaxed);
         SURFACE_HEIGHT.store(dims.height, Ordering::Relaxed);
 
+        // screens read the recording on every update, so existing ones are attached to it already
+        let state = RECORDER.lock().resume_ui();
+
         //nice place to create first screen
         if self.screens.is_empty() {
-            self.screens.push(Box::new(MainScreen::new(gl.clone(), self.exit_request.clone())));
+            info!("[AppState] Building screens for {:?}", state);
+            match state {
+                RecordingState::Idle => {
+                    self.screens.push(Box::new(MainScreen::new(gl.clone(), self.exit_request.clone())));
+                }
+                RecordingState::Recording => {
+                    self.screens.push(Box::new(ActiveTrainingScreen::new(gl.clone(), self.exit_request.clone())));
+                }
+                RecordingState::Paused => {
+                    self.screens.push(Box::new(ActiveTrainingScreen::new(gl.clone(), self.exit_request.clone())));
+                    self.screens.push(Box::new(PausedScreen::new(gl.clone(), self.exit_request.clone())));
+                }
+            }
         }
     }
 
+    /// Window surface is gone, nothing is drawn until the next [`Self::ensure_renderer`].
+    /// The recording goes on.
+    pub fn suspend(&mut self) {
+        RECORDER.lock().suspend_ui();
+    }
+
     // called repeatedly just before draw, to determine, should we draw
     pub fn renderer_ready(&self) -> bool {
         !self.screens.is_empty()
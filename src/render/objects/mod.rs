@@ -12,6 +12,9 @@ pub mod animated_image;
 pub mod textbox;
 pub mod start_animation;
 pub mod tab;
+pub mod polyline;
+pub mod route_map;
+pub mod track_map;
 
 
 #[rustfmt::skip]
@@ -0,0 +1,10 @@
+#version 300 es
+precision highp float;
+
+in vec2 position;
+
+uniform float y_ratio;
+
+void main() {
+    gl_Position = vec4(position.x * 2.0 - 1.0, position.y * 2.0 / y_ratio - 1.0, 0.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+use std::mem;
+use std::sync::Arc;
+use crate::render::{check_gl_errors, create_shader, get_surface_y_ratio, gl};
+use crate::render::gl::types::{GLint, GLsizei, GLsizeiptr, GLuint};
+
+/// Line strip through points in screen units, x 0..1 and y 0..y_ratio
+pub struct Polyline {
+    gl: Arc<gl::Gl>,
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    fbo: GLuint,
+
+    u_color_loc: GLint,
+    width: f32,
+    count: usize,
+}
+
+impl Polyline {
+    pub fn new(gl: Arc<gl::Gl>, color: (f32, f32, f32, f32), width: f32) -> Self {
+        unsafe {
+            let vertex_shader = create_shader(&gl, gl::VERTEX_SHADER, include_bytes!("polyline-vert.glsl"));
+            let fragment_shader = create_shader(&gl, gl::FRAGMENT_SHADER, include_bytes!("polyline-frag.glsl"));
+
+            let program = gl.CreateProgram();
+
+            gl.AttachShader(program, vertex_shader);
+            gl.AttachShader(program, fragment_shader);
+
+            gl.LinkProgram(program);
+
+            gl.UseProgram(program);
+
+            gl.DeleteShader(vertex_shader);
+            gl.DeleteShader(fragment_shader);
+
+            let mut fbo = 0;
+            gl.GenFramebuffers(1, &mut fbo);
+
+            let mut vao = mem::zeroed();
+            gl.GenVertexArrays(1, &mut vao);
+            gl.BindVertexArray(vao);
+
+            let mut vbo = mem::zeroed();
+            gl.GenBuffers(1, &mut vbo);
+            gl.BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let ratio_location = gl.GetUniformLocation(program, b"y_ratio\0".as_ptr() as *const _);
+            gl.Uniform1f(ratio_location, get_surface_y_ratio() as f32);
+
+            let u_color_loc = gl.GetUniformLocation(program, b"color\0".as_ptr() as *const _);
+            gl.Uniform4f(u_color_loc, color.0, color.1, color.2, color.3);
+
+            let pos_attrib = gl.GetAttribLocation(program, b"position\0".as_ptr() as *const _);
+            gl.VertexAttribPointer(
+                pos_attrib as GLuint,
+                2,
+                gl::FLOAT,
+                0,
+                2 * mem::size_of::<f32>() as GLsizei,
+                std::ptr::null(),
+            );
+            gl.EnableVertexAttribArray(pos_attrib as GLuint);
+            check_gl_errors(&gl);
+
+            Self {
+                gl,
+                program,
+                vao,
+                vbo,
+                fbo,
+                u_color_loc,
+                width,
+                count: 0,
+            }
+        }
+    }
+
+    pub fn set_points(&mut self, points: &[(f64, f64)]) {
+        let vert_data: Vec<f32> = points.iter().flat_map(|&(x, y)| [x as f32, y as f32]).collect();
+        self.count = points.len();
+        unsafe {
+            self.gl.BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            self.gl.BufferData(
+                gl::ARRAY_BUFFER,
+                (vert_data.len() * mem::size_of::<f32>()) as GLsizeiptr,
+                vert_data.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+    }
+
+    pub fn set_color(&mut self, color: (f32, f32, f32, f32)) {
+        unsafe {
+            self.gl.UseProgram(self.program);
+            self.gl.Uniform4f(self.u_color_loc, color.0, color.1, color.2, color.3);
+        }
+    }
+
+    #[profiling::function]
+    pub fn draw(&self, target_texture: GLuint) {
+        if self.count < 2 {
+            return;
+        }
+        let gl = &self.gl;
+        unsafe {
+            gl.UseProgram(self.program);
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, target_texture, 0);
+
+            gl.BindVertexArray(self.vao);
+            gl.BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+            gl.LineWidth(self.width);
+            gl.DrawArrays(gl::LINE_STRIP, 0, self.count as GLsizei);
+        }
+    }
+}
+
+impl Drop for Polyline {
+    fn drop(&mut self) {
+        let gl = &self.gl;
+
+        unsafe {
+            gl.DeleteProgram(self.program);
+            gl.DeleteVertexArrays(1, &self.vao);
+            gl.DeleteBuffers(1, &self.vbo);
+            gl.DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
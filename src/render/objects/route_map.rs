@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use crate::render::gl;
+use crate::render::gl::types::GLuint;
+use crate::render::objects::polyline::Polyline;
+use crate::render::utils::position::FreePosition;
+
+/// Half size of the position marker, screen units
+const MARKER_SIZE: f64 = 0.012;
+
+/// Scale and offset that fit (north, east) meters into a box on screen, keeping the aspect ratio
+#[derive(Clone, Copy, Debug)]
+pub struct MapFit {
+    min_north: f64,
+    min_east: f64,
+    /// screen units per meter
+    scale: f64,
+    pad_x: f64,
+    pad_y: f64,
+}
+
+impl MapFit {
+    /// `None` for fewer than two distinct points
+    pub fn new<'a>(points: impl Iterator<Item = &'a (f64, f64)> + Clone, (left, bottom, width, height): (f64, f64, f64, f64)) -> Option<Self> {
+        let min_n = points.clone().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_n = points.clone().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let min_e = points.clone().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_e = points.map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let span = ((max_n - min_n) / height).max((max_e - min_e) / width);
+        if !span.is_finite() || span <= 0.0 {
+            return None;
+        }
+        let scale = 1.0 / span;
+        Some(MapFit {
+            min_north: min_n,
+            min_east: min_e,
+            scale,
+            pad_x: left + (width - (max_e - min_e) * scale) / 2.0,
+            pad_y: bottom + (height - (max_n - min_n) * scale) / 2.0,
+        })
+    }
+
+    pub fn to_screen(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        points.iter().map(|&(n, e)| (self.pad_x + (e - self.min_east) * self.scale, self.pad_y + (n - self.min_north) * self.scale)).collect()
+    }
+
+    /// Diamond around `position`
+    pub fn marker(&self, position: (f64, f64)) -> Vec<(f64, f64)> {
+        let (x, y) = self.to_screen(&[position])[0];
+        let s = MARKER_SIZE;
+        vec![(x - s, y), (x, y + s), (x + s, y), (x, y - s), (x - s, y)]
+    }
+}
+
+/// Planned route fitted into a box, with the covered part and the current position on top
+pub struct RouteMap {
+    route: Polyline,
+    covered: Polyline,
+    marker: Polyline,
+
+    bounds: (f64, f64, f64, f64),
+    fit: Option<MapFit>,
+}
+
+impl RouteMap {
+    pub fn new(gl: Arc<gl::Gl>, pos: FreePosition) -> Self {
+        Self {
+            route: Polyline::new(gl.clone(), (1.0, 1.0, 1.0, 0.35), 4.0),
+            covered: Polyline::new(gl.clone(), (0.3, 0.9, 0.4, 1.0), 6.0),
+            marker: Polyline::new(gl, (1.0, 0.8, 0.1, 1.0), 6.0),
+            bounds: pos.get(),
+            fit: None,
+        }
+    }
+
+    /// Route as (north, east) meters, keeps the aspect ratio
+    pub fn set_route(&mut self, points: &[(f64, f64)]) {
+        self.fit = MapFit::new(points.iter(), self.bounds);
+        if let Some(fit) = self.fit {
+            self.route.set_points(&fit.to_screen(points));
+        }
+    }
+
+    /// Covered part of the route and the current position, in the units of the route
+    pub fn set_progress(&mut self, covered: &[(f64, f64)], position: Option<(f64, f64)>) {
+        let Some(fit) = self.fit else {
+            return;
+        };
+        self.covered.set_points(&fit.to_screen(covered));
+        self.marker.set_points(&position.map(|position| fit.marker(position)).unwrap_or_default());
+    }
+
+    pub fn draw(&self, texture_id: GLuint) {
+        if self.fit.is_none() {
+            return;
+        }
+        self.route.draw(texture_id);
+        self.covered.draw(texture_id);
+        self.marker.draw(texture_id);
+    }
+}
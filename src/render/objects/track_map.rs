@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use crate::render::gl;
+use crate::render::gl::types::GLuint;
+use crate::render::objects::polyline::Polyline;
+use crate::render::objects::route_map::MapFit;
+use crate::render::utils::position::FreePosition;
+
+/// Recorded track fitted into a box, one line per segment and the latest point marked
+pub struct TrackMap {
+    gl: Arc<gl::Gl>,
+    segments: Vec<Polyline>,
+    marker: Polyline,
+    /// number of segments with points, the rest of `segments` is kept for reuse
+    shown: usize,
+
+    bounds: (f64, f64, f64, f64),
+}
+
+impl TrackMap {
+    pub fn new(gl: Arc<gl::Gl>, pos: FreePosition) -> Self {
+        Self {
+            marker: Polyline::new(gl.clone(), (1.0, 0.8, 0.1, 1.0), 6.0),
+            gl,
+            segments: Vec::new(),
+            shown: 0,
+            bounds: pos.get(),
+        }
+    }
+
+    /// Segments as (north, east) meters, refitted to the whole track on every call
+    pub fn set_track(&mut self, segments: &[Vec<(f64, f64)>]) {
+        let Some(fit) = MapFit::new(segments.iter().flatten(), self.bounds) else {
+            self.shown = 0;
+            return;
+        };
+        while self.segments.len() < segments.len() {
+            self.segments.push(Polyline::new(self.gl.clone(), (0.3, 0.9, 0.4, 1.0), 5.0));
+        }
+        for (line, points) in self.segments.iter_mut().zip(segments) {
+            line.set_points(&fit.to_screen(points));
+        }
+        self.shown = segments.len();
+        let last = segments.iter().rev().find_map(|points| points.last());
+        self.marker.set_points(&last.map(|&position| fit.marker(position)).unwrap_or_default());
+    }
+
+    pub fn draw(&self, texture_id: GLuint) {
+        if self.shown == 0 {
+            return;
+        }
+        for line in &self.segments[..self.shown] {
+            line.draw(texture_id);
+        }
+        self.marker.draw(texture_id);
+    }
+}
@@ -7,7 +7,9 @@ use crate::render::images::{get_gif, get_image};
 use crate::render::objects::animated_image::AnimatedImage;
 use crate::render::objects::image::Image;
 use crate::render::objects::r#box::Squad;
+use crate::render::objects::route_map::RouteMap;
 use crate::render::objects::tab::Tab;
+use crate::render::objects::track_map::TrackMap;
 use crate::render::objects::textbox::TextBox;
 use crate::render::screens::{ScreenManagementCmd, ScreenRendering, ScreenTrait};
 
@@ -15,194 +17,32 @@ use crate::render::screens::{ScreenManagementCmd, ScreenRendering, ScreenTrait};
 use crate::render::utils::circle_animation::CircleAnimation;
 use crate::render::utils::position::{FixedPosition, FreePosition};
 
-
-use jni::JNIEnv;
-use jni::objects::JClass;
-use jni::sys::jdouble;
-use lazy_static::lazy_static;
-use log::{info, warn};
-use parking_lot::Mutex;
 use crate::render::screens::paused_screen::PausedScreen;
-
-#[derive(Clone)]
-pub struct LocationMetric {
-    latitude: f64,
-    longitude: f64,
-    accuracy: f64,
-    timestamp: f64,
-}
-
-
-pub struct GpsData {
-    available_since: Option<Instant>,
-    gps_acc_good: bool,
-    last_known_acc: Option<f64>,
-    initial_metric: Option<LocationMetric>,
-    all_metrics: Vec<LocationMetric>,
-    total_time: f64,
-    total_distance: f64,
-    paused: bool,
-}
-
-impl GpsData {
-    fn new() -> Self {
-        GpsData {
-            available_since: None,
-            initial_metric: None,
-            gps_acc_good: false,
-            last_known_acc: None,
-            all_metrics: Vec::new(),
-            total_time: 0.0,
-            total_distance: 0.0,
-            paused: false
-        }
-    }
-
-    fn update_location(&mut self, latitude: f64, longitude: f64, accuracy: f64, timestamp: f64) {
-        if self.paused {
-            return;
-        }
-
-        if self.available_since.is_none() {
-            self.available_since = Some(Instant::now());
-        }
-
-        let metric = LocationMetric {
-            latitude,
-            longitude,
-            accuracy,
-            timestamp,
-        };
-
-
-        self.last_known_acc = Some(accuracy);
-        if let Some(available_since) = &self.available_since {
-            let elapsed = Instant::now().duration_since(*available_since).as_secs_f64();
-            if elapsed < 10.0 {
-                return;
-            }
-        }
-
-        if accuracy <= 5.5 {
-            self.gps_acc_good = true;
-            if self.initial_metric.is_none(){
-                self.initial_metric = Some(metric.clone());
-                info!("Initial metric recorded! Training is started");
-            }
-
-            if let Some(initial_metric) = &self.initial_metric {
-                let lat_offset = (metric.latitude - initial_metric.latitude) * 111_319.5;
-                let lon_offset = (metric.longitude - initial_metric.longitude) * (111_319.5 * initial_metric.latitude.to_radians().cos());
-
-
-                if let Some(prev_metric) = self.all_metrics.last() {
-                    let distance = ((lat_offset - prev_metric.latitude).powi(2) + (lon_offset - prev_metric.longitude).powi(2)).sqrt();
-                    self.total_distance += distance;
-
-                    let time_diff = metric.timestamp - prev_metric.timestamp;
-                    self.total_time += time_diff;
-                }
-                info!("Offset: Lat: {}, Lon: {}", lat_offset, lon_offset);
-
-                self.all_metrics.push(LocationMetric {
-                    latitude: lat_offset,
-                    longitude: lon_offset,
-                    accuracy: metric.accuracy,
-                    timestamp: metric.timestamp,
-                });
-
-                info!("\nTotal time: {}, total distance: {}", self.total_time, self.total_distance);
-                info!("\nAvg speed: {}", self.avg_speed());
-            }
-        }
-        else {
-            self.gps_acc_good = false;
-        }
-    }
-
-    fn has_initial_metric(&self) -> bool {
-        self.initial_metric.is_some()
-    }
-
-    fn gps_online(&self) -> bool {
-        self.available_since.is_some()
-    }
-
-    fn is_good_accuracy(&self) -> bool {
-        self.gps_acc_good
-    }
-
-    pub fn avg_speed(&self) -> f64 {
-        if self.total_time == 0.0 {
-            return 0.0;
-        }
-        self.total_distance / self.total_time
-    }
-
-    pub fn total_time(&self) -> f64 {
-        self.total_time
-    }
-
-    pub fn total_distance(&self) -> f64 {
-        self.total_distance
-    }
-
-    fn get_last_known_acc(&self) -> Option<f64> {
-        self.last_known_acc
-    }
-
-    pub fn pause(&mut self) {
-        self.paused = true;
-        self.initial_metric = None;
-        self.all_metrics.clear();
-        self.last_known_acc = None;
-        self.available_since = None;
-    }
-
-    pub(crate) fn resume(&mut self) {
-        self.paused = false;
-    }
-}
-
-lazy_static! {
-    pub static ref GPS_DATA: Mutex<GpsData> = Mutex::new(GpsData::new());
+use crate::tracking::laps::{format_duration, format_pace};
+use crate::tracking::{FixRejection, RECORDER, SessionEvent};
+use crate::tracking::cues::banner::current_banner;
+use crate::tracking::workout::PaceCheck;
+use log::info;
+
+const TAB_COLORS: [(f32, f32, f32); 3] = [(0.05, 0.2, 0.3), (0.15, 0.1, 0.3), (0.3, 0.05, 0.3)];
+const TAB_OFFSETS: [f32; 3] = [0.2, 0.4, 0.6];
+const TABS_TOP: f64 = 1.3;
+/// height of a collapsed tab, only its ear is visible
+const TAB_COLLAPSED: f64 = 0.12;
+/// seconds between redraws of the recorded track, simplifying it is not free
+const TRACK_REFRESH: f64 = 2.0;
+
+/// Top side of every tab when `selected` tab is expanded
+fn tab_tops(selected: usize) -> [f64; 3] {
+    let expanded = TABS_TOP - 2.0 * TAB_COLLAPSED;
+    let strip = |i: usize| if i == selected { expanded } else { TAB_COLLAPSED };
+    [TABS_TOP, TABS_TOP - strip(0), TABS_TOP - strip(0) - strip(1)]
 }
 
-#[no_mangle]
-pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onLocationUpdate(
-    _env: JNIEnv,
-    _class: JClass,
-    latitude: jdouble,
-    longitude: jdouble,
-    acc: jdouble,
-    timestamp: jdouble
-) {
-    // Handle the location update
-    println!("Received location update:\n{}:  Lat {}, Lon {}. Acc: {}", timestamp,  latitude, longitude, acc);
-    let mut gps_data = GPS_DATA.lock();
-    gps_data.update_location(latitude, longitude, acc, timestamp);
-}
-
-
-#[no_mangle]
-pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onProviderEnabled(
-    _env: JNIEnv,
-    _class: JClass,
-) {
-    info!("GPS provider enabled!");
-    let mut gps_data = GPS_DATA.lock();
-    gps_data.available_since = Some(Instant::now());
-}
-
-
-#[no_mangle]
-pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onProviderDisabled(
-    _env: JNIEnv,
-    _class: JClass,
-) {
-    warn!("GPS provider disabled!");
-    let mut gps_data = GPS_DATA.lock();
-    gps_data.available_since = None;
+fn create_tab(gl: Arc<gl::Gl>, i: usize, top: f64) -> Tab {
+    let pos = FreePosition::new().bottom(-0.5).left(0.0).width(1.0)
+        .height(top + 0.5);
+    Tab::new(gl, TAB_COLORS[i], pos, TAB_OFFSETS[i])
 }
 
 pub struct ActiveTrainingScreen {
@@ -220,6 +60,27 @@ pub struct ActiveTrainingScreen {
     gps_text: TextBox,
     gps_acc_text: TextBox,
 
+    lap_but: TextBox,
+    lap_text: TextBox,
+    /// progress towards the goal, empty without one
+    goal_text: TextBox,
+    /// workout step in progress, empty without a workout
+    step_text: TextBox,
+    /// lead over the raced ghost, empty without one
+    ghost_text: TextBox,
+    /// progress along the planned route, empty without one
+    route_text: TextBox,
+    route_map: Option<RouteMap>,
+    /// distance along the route shown on the map
+    route_along: f64,
+    /// recorded track, shown in place of the route map for sessions without a route
+    track_map: Option<TrackMap>,
+    /// (display points, when) of the track on the map
+    track_shown: (usize, Instant),
+    /// latest coaching cue, shown for a few seconds
+    cue_text: TextBox,
+
+    selected_tab: usize,
     tab1: Tab,
     tab2: Tab,
     tab3: Tab,
@@ -234,6 +95,23 @@ pub struct ActiveTrainingScreen {
 
     total_dist_val: TextBox,
     total_dist_units: TextBox,
+
+    moving_time_val: TextBox,
+    moving_time_units: TextBox,
+
+    //cur
+    cur_speed_val: TextBox,
+    cur_speed_units: TextBox,
+
+    cur_pace_val: TextBox,
+    cur_pace_units: TextBox,
+
+    //avg
+    avg_speed_val: TextBox,
+    avg_speed_units: TextBox,
+
+    avg_pace_val: TextBox,
+    avg_pace_units: TextBox,
 }
 
 impl ActiveTrainingScreen {
@@ -248,40 +126,64 @@ impl ActiveTrainingScreen {
         let sparky_stones = get_font("sparky-stones").unwrap();
         let queensides = get_font("queensides").unwrap();
 
-        let mut pos = FreePosition::new().bottom(-0.5).left(0.0).width(1.0)
-            .height(1.8);
-        let tab1 = Tab::new(gl.clone(), (0.05, 0.2, 0.3), pos, 0.2);
+        let tops = tab_tops(0);
+        let tab1 = create_tab(gl.clone(), 0, tops[0]);
+        let tab2 = create_tab(gl.clone(), 1, tops[1]);
+        let tab3 = create_tab(gl.clone(), 2, tops[2]);
 
-        pos = pos.height(1.45);
-        let tab2 = Tab::new(gl.clone(), (0.15, 0.1, 0.3), pos, 0.4);
-
-        pos = pos.height(1.1);
-        let tab3 = Tab::new(gl.clone(), (0.3, 0.05, 0.3), pos, 0.6);
-
-        let tab_label_1 = TextBox::new(gl.clone(), sparky_stones.clone(), "total".to_string(), (0.25, 1.21), 0.5, 0);
-        let tab_label_2 = TextBox::new(gl.clone(), sparky_stones.clone(), "cur".to_string(), (0.47, 0.86), 0.5, 0);
-        let tab_label_3 = TextBox::new(gl.clone(), sparky_stones.clone(), "avg".to_string(), (0.67, 0.51), 0.5, 0);
+        let tab_label_1 = TextBox::new(gl.clone(), sparky_stones.clone(), "total".to_string(), (TAB_OFFSETS[0] + 0.05, (tops[0] - 0.09) as f32), 0.5, 0);
+        let tab_label_2 = TextBox::new(gl.clone(), sparky_stones.clone(), "cur".to_string(), (TAB_OFFSETS[1] + 0.07, (tops[1] - 0.09) as f32), 0.5, 0);
+        let tab_label_3 = TextBox::new(gl.clone(), sparky_stones.clone(), "avg".to_string(), (TAB_OFFSETS[2] + 0.07, (tops[2] - 0.09) as f32), 0.5, 0);
 
         let play = Image::new(gl.clone(), get_image("play").unwrap(),
                               FixedPosition::new().bottom(1.7).width(0.25).left(0.15), Some((0.1, 0.9, 0.3)));
-        let walking_gif = AnimatedImage::new(gl.clone(), get_gif("walking").unwrap(),
+
+        // attaches to the recording in progress, it is started before the screen is created
+        let (activity, route_map) = {
+            let recorder = RECORDER.lock();
+            let route_map = recorder.session().route().map(|route| {
+                let mut map = RouteMap::new(gl.clone(), FreePosition::new().left(0.6).bottom(0.05).width(0.35).height(0.45));
+                map.set_route(route.points());
+                map
+            });
+            (recorder.session().activity(), route_map)
+        };
+        let track_map = route_map.is_none().then(|| TrackMap::new(gl.clone(), FreePosition::new().left(0.6).bottom(0.05).width(0.35).height(0.45)));
+        let walking_gif = AnimatedImage::new(gl.clone(), get_gif(activity.gif_name()).unwrap(),
                                              FixedPosition::new().bottom(1.7).width(0.55).left(0.45), 0.08);
 
         let total_time_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.1, 1.05), 1.0, 0);
-        let total_time_units = TextBox::new(gl.clone(), queensides.clone(), "min:sec".to_string(), (0.1, 0.95), 1.0, 0);
+        let total_time_units = TextBox::new(gl.clone(), queensides.clone(), "elapsed".to_string(), (0.1, 0.95), 1.0, 0);
 
         let total_dist_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.75, 1.05), 1.0, 0);
         let total_dist_units = TextBox::new(gl.clone(), queensides.clone(), "m".to_string(), (0.76, 0.95), 1.0, 0);
 
-        let gps_text = TextBox::new(gl.clone(), queensides.clone(), "GPS status: waiting...".to_string(), (0.03, 1.55), 0.8, 1);
+        let moving_time_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.1, 0.75), 1.0, 0);
+        let moving_time_units = TextBox::new(gl.clone(), queensides.clone(), "moving".to_string(), (0.1, 0.65), 1.0, 0);
 
-        let gps_acc_text = TextBox::new(gl.clone(), queensides.clone(), "ACC: unknown".to_string(), (0.03, 1.45), 0.6, 0);
+        let cur_speed_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.1, 1.05), 1.0, 0);
+        let cur_speed_units = TextBox::new(gl.clone(), queensides.clone(), "km/h".to_string(), (0.1, 0.95), 1.0, 0);
 
+        let cur_pace_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.7, 1.05), 1.0, 0);
+        let cur_pace_units = TextBox::new(gl.clone(), queensides.clone(), "min/km".to_string(), (0.7, 0.95), 1.0, 0);
 
-        //reset training
-        let mut gps_data = GPS_DATA.lock();
-        *gps_data = GpsData::new();
+        let avg_speed_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.1, 1.05), 1.0, 0);
+        let avg_speed_units = TextBox::new(gl.clone(), queensides.clone(), "km/h".to_string(), (0.1, 0.95), 1.0, 0);
 
+        let avg_pace_val = TextBox::new(gl.clone(), queensides.clone(), "-".to_string(), (0.7, 1.05), 1.0, 0);
+        let avg_pace_units = TextBox::new(gl.clone(), queensides.clone(), "min/km".to_string(), (0.7, 0.95), 1.0, 0);
+
+        let gps_text = TextBox::new(gl.clone(), queensides.clone(), "GPS status: waiting...".to_string(), (0.03, 1.55), 0.8, 1);
+
+        let gps_acc_text = TextBox::new(gl.clone(), queensides.clone(), "ACC: unknown".to_string(), (0.03, 1.45), 0.6, 0);
+
+        let lap_but = TextBox::new(gl.clone(), queensides.clone(), "+Lap".to_string(), (0.8, 1.34), 0.9, 1);
+        let lap_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.03, 1.34), 0.6, 0);
+        let goal_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.1, 0.4), 0.6, 0);
+        let ghost_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.1, 0.5), 0.6, 0);
+        let step_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.1, 0.3), 0.6, 0);
+        let route_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.1, 0.1), 0.6, 0);
+        let cue_text = TextBox::new(gl.clone(), queensides.clone(), "".to_string(), (0.1, 0.2), 0.7, 0);
 
 
         ActiveTrainingScreen {
@@ -295,6 +197,19 @@ impl ActiveTrainingScreen {
             gps_text,
             gps_acc_text,
 
+            lap_but,
+            lap_text,
+            goal_text,
+            step_text,
+            ghost_text,
+            route_text,
+            route_map,
+            route_along: -1.0,
+            track_map,
+            track_shown: (0, Instant::now()),
+            cue_text,
+
+            selected_tab: 0,
             tab1,
             tab2,
             tab3,
@@ -310,6 +225,51 @@ impl ActiveTrainingScreen {
             total_time_units,
             total_dist_val,
             total_dist_units,
+            moving_time_val,
+            moving_time_units,
+
+            cur_speed_val,
+            cur_speed_units,
+            cur_pace_val,
+            cur_pace_units,
+
+            avg_speed_val,
+            avg_speed_units,
+            avg_pace_val,
+            avg_pace_units,
+        }
+    }
+
+    /// Expands tab `i` and collapses the others to their ears
+    fn select_tab(&mut self, i: usize) {
+        if i == self.selected_tab {
+            return;
+        }
+        self.selected_tab = i;
+
+        let tops = tab_tops(i);
+        self.tab1 = create_tab(self.gl.clone(), 0, tops[0]);
+        self.tab2 = create_tab(self.gl.clone(), 1, tops[1]);
+        self.tab3 = create_tab(self.gl.clone(), 2, tops[2]);
+
+        self.tab_label_1.set_pos((TAB_OFFSETS[0] + 0.05, (tops[0] - 0.09) as f32));
+        self.tab_label_2.set_pos((TAB_OFFSETS[1] + 0.07, (tops[1] - 0.09) as f32));
+        self.tab_label_3.set_pos((TAB_OFFSETS[2] + 0.07, (tops[2] - 0.09) as f32));
+
+        let val_y = (tops[i] - 0.25) as f32;
+        let units_y = (tops[i] - 0.35) as f32;
+        let (left_val, left_units, right_val, right_units) = match i {
+            0 => (&mut self.total_time_val, &mut self.total_time_units, &mut self.total_dist_val, &mut self.total_dist_units),
+            1 => (&mut self.cur_speed_val, &mut self.cur_speed_units, &mut self.cur_pace_val, &mut self.cur_pace_units),
+            _ => (&mut self.avg_speed_val, &mut self.avg_speed_units, &mut self.avg_pace_val, &mut self.avg_pace_units),
+        };
+        left_val.set_pos((0.1, val_y));
+        left_units.set_pos((0.1, units_y));
+        right_val.set_pos((if i == 0 { 0.75 } else { 0.7 }, val_y));
+        right_units.set_pos((if i == 0 { 0.76 } else { 0.7 }, units_y));
+        if i == 0 {
+            self.moving_time_val.set_pos((0.1, val_y - 0.3));
+            self.moving_time_units.set_pos((0.1, units_y - 0.3));
         }
     }
 }
@@ -317,24 +277,115 @@ impl ActiveTrainingScreen {
 impl ScreenTrait for ActiveTrainingScreen {
     fn press(&mut self, pos: (f64, f64)) -> ScreenManagementCmd {
         if pos.1 > 1.7 && pos.1 < 1.95 && pos.0 > 0.15 && pos.0 < 0.4  {
-            let mut gps_data = GPS_DATA.lock();
-            gps_data.pause();
+            RECORDER.lock().pause();
             return ScreenManagementCmd::PushScreen(Box::new(PausedScreen::new(self.gl.clone(), self.exit_request.clone())));
         }
+        // tabs, tapping anywhere in a tab strip expands it
+        if pos.1 < TABS_TOP {
+            let tops = tab_tops(self.selected_tab);
+            let tab = (0..3).find(|&i| i == 2 || pos.1 >= tops[i + 1]).unwrap();
+            self.select_tab(tab);
+            return ScreenManagementCmd::None;
+        }
+        // lap button
+        if pos.1 > 1.3 && pos.1 < 1.42 && pos.0 > 0.75 {
+            RECORDER.lock().mark_lap();
+        }
         ScreenManagementCmd::None
     }
     fn back(&mut self) -> ScreenManagementCmd {
-        let mut gps_data = GPS_DATA.lock();
-        gps_data.pause();
+        RECORDER.lock().pause();
         ScreenManagementCmd::PushScreen(Box::new(PausedScreen::new(self.gl.clone(), self.exit_request.clone())))
     }
     #[profiling::function]
     fn update(&mut self) -> ScreenManagementCmd {
-        let gps_data = GPS_DATA.lock();
+        let mut recorder = RECORDER.lock();
+        recorder.check_cues();
+        let gps_data = recorder.session_mut();
+
+        for event in gps_data.take_events() {
+            info!("Session event: {:?}", event);
+            match event {
+                // status is read from the session below, it must survive the screen being rebuilt
+                SessionEvent::AutoPaused | SessionEvent::AutoResumed => {}
+                SessionEvent::Split(split) => {
+                    self.lap_text.set_text(format!("{:.0}m split {}: {}/km", split.distance, split.index + 1, format_pace(split.pace)));
+                }
+                SessionEvent::Lap(lap) => {
+                    self.lap_text.set_text(format!("Lap {}: {}, {}/km", lap.index + 1, format_duration(lap.duration), format_pace(lap.pace)));
+                }
+                SessionEvent::GoalReached(goal) => {
+                    self.lap_text.set_text(format!("Goal {} reached!", goal));
+                }
+                SessionEvent::OffCourse(error) => {
+                    self.lap_text.set_text(format!("Off course by {:.0}m!", error));
+                }
+                SessionEvent::BackOnCourse => self.lap_text.set_text("Back on course".to_string()),
+                SessionEvent::StepFinished(step) => {
+                    self.lap_text.set_text(format!("Step {} done: {:.0}m in {}, {}/km", step.index + 1, step.distance, format_duration(step.duration), format_pace(step.pace)));
+                }
+            }
+        }
+
+        if let Some(progress) = gps_data.goal_progress() {
+            self.goal_text.set_text(format!("Goal {}: {}", progress.goal, progress));
+        }
+        if let Some(workout) = gps_data.workout() {
+            let text = match gps_data.workout_step() {
+                Some(step) => {
+                    let pace = gps_data.current_pace();
+                    match step.step.pace.filter(|_| pace > 0.0).map(|band| band.check(pace)) {
+                        Some(PaceCheck::TooFast) => format!("{} - too fast", step),
+                        Some(PaceCheck::TooSlow) => format!("{} - too slow", step),
+                        _ => step.to_string(),
+                    }
+                }
+                None => format!("{} done!", workout.name),
+            };
+            self.step_text.set_text(text);
+        }
+        if let Some(gap) = gps_data.ghost_gap() {
+            self.ghost_text.set_text(gap.to_string());
+        }
+        if let (Some(route), Some(progress)) = (gps_data.route(), gps_data.route_progress()) {
+            let text = if progress.off_course {
+                format!("Off course {:.0}m, {:.2} km left", progress.cross_track, progress.remaining / 1000.0)
+            } else {
+                format!("Route {:.2} / {:.2} km", progress.along / 1000.0, route.length() / 1000.0)
+            };
+            self.route_text.set_text(text);
+            if let Some(map) = &mut self.route_map {
+                if progress.along != self.route_along || progress.off_course {
+                    self.route_along = progress.along;
+                    map.set_progress(&route.covered(), route.position());
+                }
+            }
+        }
+        if let Some(map) = &mut self.track_map {
+            let points = gps_data.segments().iter().map(|segment| segment.display.len()).sum();
+            if points != self.track_shown.0 && self.track_shown.1.elapsed().as_secs_f64() >= TRACK_REFRESH {
+                self.track_shown = (points, Instant::now());
+                map.set_track(&gps_data.display_track());
+            }
+        }
+        self.cue_text.set_text(current_banner().unwrap_or_default());
+
+        let speed = gps_data.current_speed();
+        self.cur_speed_val.set_text(format!("{:.1}", speed * 3.6));
+        self.cur_pace_val.set_text(format_pace(gps_data.current_pace()));
+        self.avg_speed_val.set_text(format!("{:.1}", gps_data.avg_speed() * 3.6));
+        self.avg_pace_val.set_text(format_pace(gps_data.avg_pace()));
 
         if gps_data.gps_online() {
             if gps_data.has_initial_metric() {
-                if gps_data.is_good_accuracy() {
+                let gap = gps_data.estimated_now().and_then(|now| gps_data.current_gap(now));
+                if gps_data.is_auto_paused() {
+                    self.gps_text.set_text("GPS status: auto-paused".to_string());
+                }
+                else if let Some((duration, reason)) = gap {
+                    self.gps_text.set_text(format!("GPS status: signal lost {:.0}s ({})", duration, reason));
+                }
+                else if gps_data.is_good_accuracy() {
                     self.gps_text.set_text("GPS status: training online".to_string());
                     self.total_dist_val.set_text(format!("{:.2}", gps_data.total_distance()));
                 }
@@ -342,21 +393,28 @@ impl ScreenTrait for ActiveTrainingScreen {
                     self.gps_text.set_text("GPS status: training online (bad acc)".to_string());
                 }
 
-                let total_time = gps_data.total_time();
-                let secs = total_time as u64;
-                let mins = secs / 60;
-                let secs = secs % 60;
-                self.total_time_val.set_text(format!("{:02}:{:02}", mins, secs));
+                self.total_time_val.set_text(format_duration(gps_data.elapsed_time()));
+                self.moving_time_val.set_text(format_duration(gps_data.moving_time()));
             }
             else {
                 self.gps_text.set_text("GPS status: waiting (bad acc)".to_string());
             }
 
-            if gps_data.is_good_accuracy() {
-                self.gps_acc_text.set_text(format!("ACC: +-{:.2}m", gps_data.get_last_known_acc().unwrap()));
-            }
-            else {
-                self.gps_acc_text.set_text(format!("ACC: +-{:.2}m (not enough)", gps_data.get_last_known_acc().unwrap()));
+            if let Some(acc) = gps_data.get_last_known_acc() {
+                match gps_data.last_rejection() {
+                    Some(rejection @ FixRejection::WarmUp(_)) => {
+                        self.gps_acc_text.set_text(format!("ACC: +-{:.2}m ({})", acc, rejection));
+                    }
+                    Some(rejection @ (FixRejection::ImplausibleSpeed { .. } | FixRejection::ImplausibleAcceleration { .. } | FixRejection::Held)) => {
+                        self.gps_acc_text.set_text(format!("ACC: +-{:.2}m ({}, {} dropped)", acc, rejection, gps_data.rejected_fixes()));
+                    }
+                    _ if gps_data.is_good_accuracy() => {
+                        self.gps_acc_text.set_text(format!("ACC: +-{:.2}m", acc));
+                    }
+                    _ => {
+                        self.gps_acc_text.set_text(format!("ACC: +-{:.2}m (not enough, max {:.1}m)", acc, gps_data.accuracy_ceiling()));
+                    }
+                }
             }
         }
         else {
@@ -377,6 +435,9 @@ impl ScreenTrait for ActiveTrainingScreen {
         self.gps_text.draw(texture_id);
         self.gps_acc_text.draw(texture_id);
 
+        self.lap_but.draw(texture_id);
+        self.lap_text.draw(texture_id);
+
         self.tab1.draw(texture_id);
         self.tab2.draw(texture_id);
         self.tab3.draw(texture_id);
@@ -385,10 +446,39 @@ impl ScreenTrait for ActiveTrainingScreen {
         self.tab_label_2.draw(texture_id);
         self.tab_label_3.draw(texture_id);
 
-        self.total_time_val.draw(texture_id);
-        self.total_time_units.draw(texture_id);
-        self.total_dist_val.draw(texture_id);
-        self.total_dist_units.draw(texture_id);
+        match self.selected_tab {
+            0 => {
+                self.total_time_val.draw(texture_id);
+                self.total_time_units.draw(texture_id);
+                self.total_dist_val.draw(texture_id);
+                self.total_dist_units.draw(texture_id);
+                self.moving_time_val.draw(texture_id);
+                self.moving_time_units.draw(texture_id);
+            }
+            1 => {
+                self.cur_speed_val.draw(texture_id);
+                self.cur_speed_units.draw(texture_id);
+                self.cur_pace_val.draw(texture_id);
+                self.cur_pace_units.draw(texture_id);
+            }
+            _ => {
+                self.avg_speed_val.draw(texture_id);
+                self.avg_speed_units.draw(texture_id);
+                self.avg_pace_val.draw(texture_id);
+                self.avg_pace_units.draw(texture_id);
+            }
+        }
+        if let Some(map) = &self.route_map {
+            map.draw(texture_id);
+        }
+        if let Some(map) = &self.track_map {
+            map.draw(texture_id);
+        }
+        self.route_text.draw(texture_id);
+        self.goal_text.draw(texture_id);
+        self.step_text.draw(texture_id);
+        self.ghost_text.draw(texture_id);
+        self.cue_text.draw(texture_id);
 
         self.screen_rendering.present();
     }
@@ -5,7 +5,7 @@ use jni::JNIEnv;
 use jni::objects::JClass;
 use crate::{ACTIVITY_OBJ, JNI_ENV};
 
-use crate::render::{gl, SURFACE_HEIGHT, SURFACE_WIDTH};
+use crate::render::{ANDROID_DATA_PATH, gl, SURFACE_HEIGHT, SURFACE_WIDTH};
 use crate::render::fonts::get_font;
 use crate::render::images::get_image;
 use crate::render::objects::image::Image;
@@ -14,10 +14,12 @@ use crate::render::objects::start_animation::StartAnimation;
 use crate::render::objects::textbox::TextBox;
 use crate::render::screens::{ScreenManagementCmd, ScreenRendering, ScreenTrait};
 use crate::render::screens::active_training::ActiveTrainingScreen;
-use crate::render::screens::records::RecordsScreen;
+use crate::render::screens::records::{push_new_record, RecordsScreen};
 use crate::render::screens::stats::StatsScreen;
 use crate::render::utils::circle_animation::CircleAnimation;
 use crate::render::utils::position::{FixedPosition, FreePosition};
+use crate::tracking::{Goal, RECORDER, set_tracking_config, TRACKING_CONFIG, TrackingConfig};
+use crate::tracking::route::{planned_routes, Route};
 
 pub fn request_permission_gps() {
     let env = JNI_ENV.lock().unwrap();
@@ -66,6 +68,28 @@ pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onPermissionGrant
     LOCATION_PERMISSION_GRANTED.store(true, Ordering::Relaxed);
 }
 
+fn goal_label(config: &TrackingConfig) -> String {
+    match (&config.workout, config.goal) {
+        (Some(workout), _) => format!("Workout: {}", workout.name),
+        (None, Some(goal)) => format!("Goal: {}", goal),
+        (None, None) => "Goal: none".to_string(),
+    }
+}
+
+fn auto_pause_label(config: &TrackingConfig) -> String {
+    format!("Auto-pause: {}", if config.auto_pause_enabled() { "on" } else { "off" })
+}
+
+fn route_label(config: &TrackingConfig) -> String {
+    match &config.route {
+        // file names may hold characters the font doesn't have
+        Some(route) => format!("Route: {}", route.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { ' ' })
+            .collect::<String>()),
+        None => "Route: none".to_string(),
+    }
+}
+
 pub struct MainScreen {
     gl: Arc<gl::Gl>,
     bg_squad: Squad,
@@ -76,6 +100,21 @@ pub struct MainScreen {
     is_start_pressed: bool,
     start_animation: StartAnimation,
     start_text: TextBox,
+    /// activity picked for the next training, tap to switch
+    activity_text: TextBox,
+    /// auto-pause of the picked activity, tap to switch
+    auto_pause_text: TextBox,
+    /// goal picked for the next training, tap to switch
+    goal_text: TextBox,
+    /// planned route of the next training, tap to switch between the GPX files of the routes folder
+    route_text: TextBox,
+
+    /// training left by a killed process, resume it or save it as a record
+    recovery_text: TextBox,
+    recovery_resume_text: TextBox,
+    recovery_save_text: TextBox,
+    show_recovery: bool,
+    is_resume_pressed: bool,
 
     logo: Image,
 
@@ -109,6 +148,20 @@ impl MainScreen {
         let start_animation = StartAnimation::new(gl.clone(),
                                                   FreePosition::new().left(0.1).width(0.8).bottom(0.7).height(0.8));
 
+        let (activity, auto_pause, goal, route) = {
+            let config = TRACKING_CONFIG.lock();
+            (config.activity, auto_pause_label(&config), goal_label(&config), route_label(&config))
+        };
+        let activity_text = TextBox::new(gl.clone(), font.clone(), format!("Activity: {}", activity), (0.28, 0.51), 0.9, 0);
+        let auto_pause_text = TextBox::new(gl.clone(), font.clone(), auto_pause, (0.2, 0.43), 0.6, 0);
+        let goal_text = TextBox::new(gl.clone(), font.clone(), goal, (0.2, 0.35), 0.7, 0);
+        let route_text = TextBox::new(gl.clone(), font.clone(), route, (0.2, 0.27), 0.6, 0);
+
+        let recovery_text = TextBox::new(gl.clone(), font.clone(), "Unfinished training found".to_string(), (0.12, 0.62), 0.7, 0);
+        let recovery_resume_text = TextBox::new(gl.clone(), font.clone(), "Resume".to_string(), (0.15, 0.45), 0.9, 0);
+        let recovery_save_text = TextBox::new(gl.clone(), font.clone(), "Save".to_string(), (0.62, 0.45), 0.9, 0);
+        let show_recovery = RECORDER.lock().has_unfinished();
+
         let logo = Image::new(gl.clone(), get_image("panther_logo").unwrap(),
                               FixedPosition::new().bottom(1.75).width(0.25).left(0.65), None);
 
@@ -142,6 +195,16 @@ impl MainScreen {
             start_text,
             start_animation,
             is_start_pressed: false,
+            activity_text,
+            auto_pause_text,
+            goal_text,
+            route_text,
+
+            recovery_text,
+            recovery_resume_text,
+            recovery_save_text,
+            show_recovery,
+            is_resume_pressed: false,
 
             no_permission_text,
             show_no_permission_text: false,
@@ -161,6 +224,64 @@ impl MainScreen {
         }
     }
 
+    fn switch_activity(&mut self) {
+        let mut config = TRACKING_CONFIG.lock().clone();
+        config.activity = config.activity.next();
+        self.activity_text.set_text(format!("Activity: {}", config.activity));
+        self.auto_pause_text.set_text(auto_pause_label(&config));
+        set_tracking_config(config, ANDROID_DATA_PATH);
+    }
+
+    fn switch_auto_pause(&mut self) {
+        let mut config = TRACKING_CONFIG.lock().clone();
+        let enabled = !config.auto_pause_enabled();
+        config.set_auto_pause(enabled);
+        self.auto_pause_text.set_text(auto_pause_label(&config));
+        set_tracking_config(config, ANDROID_DATA_PATH);
+    }
+
+    fn switch_goal(&mut self) {
+        let mut config = TRACKING_CONFIG.lock().clone();
+        // goal presets come first, then the configured workouts
+        if let Some(workout) = config.workout.take() {
+            let i = config.workouts.iter().position(|w| *w == workout);
+            config.workout = i.and_then(|i| config.workouts.get(i + 1)).cloned();
+        } else {
+            config.goal = Goal::next_preset(config.goal);
+            if config.goal.is_none() {
+                config.workout = config.workouts.first().cloned();
+            }
+        }
+        self.goal_text.set_text(goal_label(&config));
+        set_tracking_config(config, ANDROID_DATA_PATH);
+    }
+
+    fn switch_route(&mut self) {
+        let mut config = TRACKING_CONFIG.lock().clone();
+        let routes = planned_routes(ANDROID_DATA_PATH);
+        let current = config.route.as_ref().and_then(|route| {
+            routes.iter().position(|path| path.file_stem().is_some_and(|stem| stem.to_string_lossy() == route.name))
+        });
+        // a route taken from a record is followed by the first file
+        let next = match (&config.route, current) {
+            (Some(_), Some(i)) => routes.get(i + 1),
+            (Some(_), None) | (None, _) => routes.first(),
+        };
+        config.route = next.and_then(|path| Route::from_gpx_file(path));
+        self.route_text.set_text(route_label(&config));
+        set_tracking_config(config, ANDROID_DATA_PATH);
+    }
+
+    fn save_unfinished(&mut self) {
+        let mut recorder = RECORDER.lock();
+        if recorder.recover() {
+            let gps_data = recorder.finish();
+            push_new_record(&gps_data);
+        }
+        recorder.discard_journal();
+        self.show_recovery = false;
+    }
+
     fn start_pressed(&mut self) {
         if self.inputs_blocked {
             return;
@@ -198,6 +319,41 @@ impl ScreenTrait for MainScreen {
             }
             ScreenManagementCmd::None
         }
+        else if self.show_recovery && pos.1 > 0.35 && pos.1 < 0.6 {
+            if !self.inputs_blocked {
+                if pos.0 < 0.5 {
+                    self.is_resume_pressed = true;
+                    request_permission_gps();
+                } else {
+                    self.save_unfinished();
+                }
+            }
+            ScreenManagementCmd::None
+        }
+        else if pos.0 > 0.1 && pos.0 < 0.9 && pos.1 > 0.49 && pos.1 < 0.6 {
+            if !self.inputs_blocked {
+                self.switch_activity();
+            }
+            ScreenManagementCmd::None
+        }
+        else if !self.show_recovery && pos.0 > 0.1 && pos.0 < 0.9 && pos.1 > 0.42 && pos.1 < 0.49 {
+            if !self.inputs_blocked {
+                self.switch_auto_pause();
+            }
+            ScreenManagementCmd::None
+        }
+        else if !self.show_recovery && pos.0 > 0.1 && pos.0 < 0.9 && pos.1 > 0.33 && pos.1 < 0.42 {
+            if !self.inputs_blocked {
+                self.switch_goal();
+            }
+            ScreenManagementCmd::None
+        }
+        else if !self.show_recovery && pos.0 > 0.1 && pos.0 < 0.9 && pos.1 > 0.25 && pos.1 < 0.33 {
+            if !self.inputs_blocked {
+                self.switch_route();
+            }
+            ScreenManagementCmd::None
+        }
         else {
             ScreenManagementCmd::None
         }
@@ -215,11 +371,22 @@ impl ScreenTrait for MainScreen {
             self.is_start_pressed = false;
         }
 
+        if LOCATION_PERMISSION_GRANTED.load(Ordering::Relaxed) && self.is_resume_pressed {
+            self.is_resume_pressed = false;
+            self.show_recovery = false;
+            let mut recorder = RECORDER.lock();
+            if recorder.recover() {
+                recorder.resume();
+                return ScreenManagementCmd::PushScreen(Box::new(ActiveTrainingScreen::new(self.gl.clone(), self.exit_request.clone())))
+            }
+        }
+
         if LOCATION_PERMISSION_DENIED.load(Ordering::Relaxed) {
             self.show_no_permission_text = true;
         }
 
         if self.start_animation.is_finished() {
+            RECORDER.lock().start(TRACKING_CONFIG.lock().session_config());
             return ScreenManagementCmd::PushScreen(Box::new(ActiveTrainingScreen::new(self.gl.clone(), self.exit_request.clone())))
         }
 
@@ -241,6 +408,18 @@ impl ScreenTrait for MainScreen {
 
         self.start_text.draw(texture_id);
         self.start_animation.draw(texture_id);
+        if self.bot_animation.is_none() {
+            if self.show_recovery {
+                self.recovery_text.draw(texture_id);
+                self.recovery_resume_text.draw(texture_id);
+                self.recovery_save_text.draw(texture_id);
+            } else {
+                self.activity_text.draw(texture_id);
+                self.auto_pause_text.draw(texture_id);
+                self.goal_text.draw(texture_id);
+                self.route_text.draw(texture_id);
+            }
+        }
 
         if let Some(start) = self.bot_animation {
             let elapsed = (Instant::now().duration_since(start).as_secs_f64() * 2.0).clamp(0.0, 3.5);
@@ -11,9 +11,9 @@ use crate::render::utils::circle_animation::CircleAnimation;
 use crate::render::utils::position::FreePosition;
 
 
-use crate::render::screens::active_training::GPS_DATA;
 use crate::render::screens::main::{MainScreen, stop_location_updates};
 use crate::render::screens::records::push_new_record;
+use crate::tracking::{clear_session_picks, RECORDER};
 
 pub struct PausedScreen {
     gl: Arc<gl::Gl>,
@@ -90,15 +90,18 @@ impl ScreenTrait for PausedScreen {
     fn press(&mut self, pos: (f64, f64)) -> ScreenManagementCmd {
         // continue button
         if pos.0 > 0.5 && pos.0 < 0.9 && pos.1 > 1.1 && pos.1 < 1.28 {
-            let mut gps_data = GPS_DATA.lock();
-            gps_data.resume();
+            RECORDER.lock().resume();
             return ScreenManagementCmd::PopScreen;
         }
         // exit button
         if pos.0 > 0.1 && pos.0 < 0.5 && pos.1 > 1.1 && pos.1 < 1.28 {
-            let mut gps_data = GPS_DATA.lock();
-            gps_data.pause();
-            push_new_record(&gps_data);
+            {
+                let mut recorder = RECORDER.lock();
+                let gps_data = recorder.finish();
+                push_new_record(&gps_data);
+                recorder.discard_journal();
+            }
+            clear_session_picks();
             stop_location_updates();
             return ScreenManagementCmd::PushScreen(Box::new(MainScreen::new(self.gl.clone(), self.exit_request.clone())));
         }
@@ -106,8 +109,7 @@ impl ScreenTrait for PausedScreen {
         ScreenManagementCmd::None
     }
     fn back(&mut self) -> ScreenManagementCmd {
-        let mut gps_data = GPS_DATA.lock();
-        gps_data.resume();
+        RECORDER.lock().resume();
         ScreenManagementCmd::PopScreen
     }
     #[profiling::function]
@@ -5,7 +5,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use lazy_static::lazy_static;
 use log::{info, warn};
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::Mutex;
 use puffin::profile_scope;
 use crate::render::{ANDROID_DATA_PATH, gl, SURFACE_HEIGHT, SURFACE_WIDTH};
 use crate::render::fonts::get_font;
@@ -15,19 +15,53 @@ use crate::render::objects::image::Image;
 use crate::render::objects::r#box::Squad;
 use crate::render::objects::textbox::TextBox;
 use crate::render::screens::{ScreenManagementCmd, ScreenRendering, ScreenTrait};
-use crate::render::screens::active_training::GpsData;
 use crate::render::screens::main::MainScreen;
 use crate::render::screens::stats::StatsScreen;
 use crate::render::utils::circle_animation::CircleAnimation;
 use crate::render::utils::position::{FixedPosition, FreePosition};
+use crate::tracking::{ActivityType, Goal, Lap, LocationMetric, set_tracking_config, StepResult, TRACKING_CONFIG, TrackingSession, USER_PROFILE};
+use crate::tracking::ghost::{Ghost, GhostCurve};
+use crate::tracking::route::Route;
 
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Record {
+    /// UTC seconds of the end of the training
     timestamp: f64,
     distance: f64,
     time: f64,
     speed: f64,
+    #[serde(default)]
+    splits: Vec<Lap>,
+    #[serde(default)]
+    laps: Vec<Lap>,
+    #[serde(default)]
+    elevation_gain: f64,
+    #[serde(default)]
+    elevation_loss: f64,
+    /// wall-clock time from start to finish, pauses included
+    #[serde(default)]
+    elapsed_time: f64,
+    #[serde(default)]
+    moving_time: f64,
+    #[serde(default)]
+    activity: ActivityType,
+    /// estimated energy expenditure, kcal
+    #[serde(default)]
+    calories: f64,
+    /// simplified track, one polyline per segment
+    #[serde(default)]
+    track: Vec<Vec<LocationMetric>>,
+    #[serde(default)]
+    goal: Option<Goal>,
+    #[serde(default)]
+    goal_met: bool,
+    /// name of the structured workout followed
+    #[serde(default)]
+    workout: Option<String>,
+    /// finished workout steps
+    #[serde(default)]
+    steps: Vec<StepResult>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -36,22 +70,44 @@ pub struct Records {
     pub total_distance: f64,
     pub total_time: f64,
     pub avg_speed: f64,
+    #[serde(default)]
+    pub total_elapsed_time: f64,
+    #[serde(default)]
+    pub total_moving_time: f64,
+    #[serde(default)]
+    pub total_calories: f64,
 }
 
-pub fn push_new_record(gps_data: &MutexGuard<GpsData>) {
+pub fn push_new_record(gps_data: &TrackingSession) {
     let mut records = RECORDS_LIST.lock();
 
-    //UNIX EPOCH
+    // the session clock is used when known, a recovered training is saved long after its end
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
     let record = Record {
-        timestamp: now,
+        timestamp: gps_data.end_utc().unwrap_or(now),
         distance: gps_data.total_distance(),
         time: gps_data.total_time(),
         speed: gps_data.avg_speed(),
+        splits: gps_data.splits().to_vec(),
+        laps: gps_data.laps().to_vec(),
+        elevation_gain: gps_data.elevation_gain(),
+        elevation_loss: gps_data.elevation_loss(),
+        elapsed_time: gps_data.elapsed_time(),
+        moving_time: gps_data.moving_time(),
+        activity: gps_data.recorded_activity(),
+        calories: gps_data.calories(&USER_PROFILE.lock()),
+        track: gps_data.storage_track(),
+        goal: gps_data.goal(),
+        goal_met: gps_data.goal_met(),
+        workout: gps_data.workout().map(|workout| workout.name.clone()),
+        steps: gps_data.step_results().to_vec(),
     };
 
     records.total_distance += record.distance;
     records.total_time += record.time;
+    records.total_elapsed_time += record.elapsed_time;
+    records.total_moving_time += record.moving_time;
+    records.total_calories += record.calories;
     if records.total_time == 0.0 {
         records.avg_speed = 0.0;
     }
@@ -81,6 +137,9 @@ lazy_static!(
         total_distance: 0.0,
         total_time: 0.0,
         avg_speed: 0.0,
+        total_elapsed_time: 0.0,
+        total_moving_time: 0.0,
+        total_calories: 0.0,
     });
 );
 
@@ -164,6 +223,57 @@ impl RecordsScreen {
     }
 }
 
+impl RecordsScreen {
+    /// Steps the `i`-th record through its roles in the next training:
+    /// none, ghost, route, ghost and route, back to none
+    fn cycle_role(&mut self, i: usize) {
+        let records = RECORDS_LIST.lock();
+        let Some(record) = records.records.get(i) else {
+            return;
+        };
+        let mut config = TRACKING_CONFIG.lock().clone();
+        let ghost = config.ghost.as_ref().is_some_and(|ghost| ghost.record == record.timestamp);
+        let route = config.route.as_ref().is_some_and(|route| is_route_of(route, record));
+        let (ghost, route) = match (ghost, route) {
+            (false, false) => (true, false),
+            (true, false) => (false, true),
+            (false, true) => (true, true),
+            (true, true) => (false, false),
+        };
+
+        if !ghost {
+            config.ghost = config.ghost.filter(|ghost| ghost.record != record.timestamp);
+        }
+        else if let Some(curve) = GhostCurve::from_track(&record.track) {
+            config.ghost = Some(Ghost {
+                record: record.timestamp,
+                curve: curve.scaled(record.distance, record.time),
+            });
+        }
+        else {
+            warn!("Record {} has no stored track to race", i);
+        }
+
+        if !route {
+            config.route = config.route.filter(|route| !is_route_of(route, record));
+        }
+        else if let Some(planned) = Route::from_track(&format!("Record {}", i), &record.track) {
+            config.route = Some(planned);
+        }
+        else {
+            warn!("Record {} has no stored track to follow", i);
+        }
+
+        info!("Record {} roles: ghost {}, route {}", i, ghost, route);
+        set_tracking_config(config, ANDROID_DATA_PATH);
+    }
+}
+
+/// Whether the planned route was taken from the stored track of `record`
+fn is_route_of(route: &Route, record: &Record) -> bool {
+    record.track.iter().flatten().map(|m| (m.latitude, m.longitude)).eq(route.points.iter().copied())
+}
+
 impl ScreenTrait for RecordsScreen {
     fn press(&mut self, pos: (f64, f64)) -> ScreenManagementCmd {
         if pos.1 < 0.25 {
@@ -182,6 +292,12 @@ impl ScreenTrait for RecordsScreen {
             }
         }
         else {
+            // tapping a record card switches its role in the next training
+            let from_first_top = 1.58 + self.scroll_offset - pos.1;
+            let i = (from_first_top / 0.3).floor();
+            if i >= 0.0 && from_first_top - 0.3 * i < 0.2 {
+                self.cycle_role(i as usize);
+            }
             ScreenManagementCmd::None
         }
     }
@@ -198,10 +314,37 @@ impl ScreenTrait for RecordsScreen {
 
         self.logo.draw(texture_id);
 
+        let (ghost, route) = {
+            let config = TRACKING_CONFIG.lock();
+            (config.ghost.as_ref().map(|ghost| ghost.record), config.route.clone())
+        };
         let records = RECORDS_LIST.lock();
         for (i, record) in records.records.iter().enumerate() {
             profile_scope!("render record");
-            let text = format!("Record {}\n{:.2}m in {:.2}s at {:.2}m/s", i, record.distance, record.time, record.speed);
+            let mut text = format!("Record {} ({})\n{:.2}m in {:.2}s at {:.2}m/s", i, record.activity, record.distance, record.time, record.speed);
+            if !record.laps.is_empty() {
+                text.push_str(&format!(", {} laps", record.laps.len()));
+            }
+            if record.elevation_gain > 0.0 || record.elevation_loss > 0.0 {
+                text.push_str(&format!(", +{:.0}m -{:.0}m", record.elevation_gain, record.elevation_loss));
+            }
+            if record.calories > 0.0 {
+                text.push_str(&format!(", {:.0} kcal", record.calories));
+            }
+            if let Some(goal) = record.goal {
+                text.push_str(&format!("\nGoal {} {}", goal, if record.goal_met { "met" } else { "missed" }));
+            }
+            if let Some(workout) = &record.workout {
+                let in_band = record.steps.iter().filter(|step| step.in_band == Some(true)).count();
+                let banded = record.steps.iter().filter(|step| step.in_band.is_some()).count();
+                text.push_str(&format!("\n{}: {} steps, {}/{} in pace", workout, record.steps.len(), in_band, banded));
+            }
+            if ghost == Some(record.timestamp) {
+                text.push_str("\nGhost of the next training");
+            }
+            if route.as_ref().is_some_and(|route| is_route_of(route, record)) {
+                text.push_str("\nRoute of the next training");
+            }
             self.record_square.set_pos_y_offset(- 0.3 * i as f64 + self.scroll_offset);
 
             self.record_info.set_text(text);
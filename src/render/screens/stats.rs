@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
-use crate::render::{gl, SURFACE_HEIGHT, SURFACE_WIDTH};
+use crate::render::{ANDROID_DATA_PATH, gl, SURFACE_HEIGHT, SURFACE_WIDTH};
 use crate::render::fonts::get_font;
 use crate::render::images::get_image;
 
@@ -13,6 +13,8 @@ use crate::render::screens::main::MainScreen;
 use crate::render::screens::records::{RECORDS_LIST, RecordsScreen};
 use crate::render::utils::circle_animation::CircleAnimation;
 use crate::render::utils::position::FixedPosition;
+use crate::tracking::laps::format_duration;
+use crate::tracking::{set_user_profile, USER_PROFILE, UserProfile};
 
 
 pub struct StatsScreen {
@@ -26,6 +28,8 @@ pub struct StatsScreen {
     logo: Image,
 
     info: TextBox,
+    /// Weight, height, age and sex rows, tap the left half to decrease and the right half to increase
+    profile_texts: [TextBox; 4],
 
     bottom_home_text: TextBox,
     bottom_records_text: TextBox,
@@ -65,6 +69,10 @@ impl StatsScreen {
                                     FixedPosition::new().bottom(0.12).height(0.08).left(0.715), Some((1.0, 0.9, 1.0)));
 
         let info = TextBox::new(gl.clone(), font.clone(), "Info...".to_string(), (0.07, 1.52), 0.95, 1);
+        let labels = profile_labels(&USER_PROFILE.lock());
+        let profile_texts = std::array::from_fn(|i| {
+            TextBox::new(gl.clone(), font.clone(), labels[i].clone(), (0.2, PROFILE_ROWS[i] as f32), 0.6, 0)
+        });
 
         StatsScreen {
             gl,
@@ -75,6 +83,7 @@ impl StatsScreen {
             cur_color,
 
             info,
+            profile_texts,
 
             logo,
 
@@ -87,6 +96,34 @@ impl StatsScreen {
             stats_icon
         }
     }
+
+    /// Steps row `row` of the user profile up or down and saves it
+    fn change_profile(&mut self, row: usize, up: bool) {
+        let mut profile = USER_PROFILE.lock().clone();
+        let step = if up { 1.0 } else { -1.0 };
+        match row {
+            0 => profile.weight = (profile.weight + step).clamp(30.0, 250.0),
+            1 => profile.height = (profile.height + step).clamp(100.0, 230.0),
+            2 => profile.age = (profile.age + step).clamp(10.0, 100.0),
+            _ => profile.sex = profile.sex.next(),
+        }
+        for (text, label) in self.profile_texts.iter_mut().zip(profile_labels(&profile)) {
+            text.set_text(label);
+        }
+        set_user_profile(profile, ANDROID_DATA_PATH);
+    }
+}
+
+/// Baselines of the profile rows, top to bottom
+const PROFILE_ROWS: [f64; 4] = [0.52, 0.45, 0.38, 0.31];
+
+fn profile_labels(profile: &UserProfile) -> [String; 4] {
+    [
+        format!("- Weight: {:.0} kg +", profile.weight),
+        format!("- Height: {:.0} cm +", profile.height),
+        format!("- Age: {:.0} +", profile.age),
+        format!("Sex: {}", profile.sex),
+    ]
 }
 
 impl ScreenTrait for StatsScreen {
@@ -106,6 +143,12 @@ impl ScreenTrait for StatsScreen {
 
             }
         }
+        else if pos.0 > 0.1 && pos.0 < 0.9 && pos.1 > 0.28 && pos.1 < 0.59 {
+            // each row spans from its baseline up to the one above
+            let row = PROFILE_ROWS.iter().position(|&y| pos.1 > y - 0.03).unwrap_or(PROFILE_ROWS.len() - 1);
+            self.change_profile(row, pos.0 > 0.5);
+            ScreenManagementCmd::None
+        }
         else {
             ScreenManagementCmd::None
         }
@@ -129,9 +172,13 @@ impl ScreenTrait for StatsScreen {
             let total_distance = records.total_distance;
             let total_time = records.total_time;
             let avg_speed = records.avg_speed;
-            self.info.set_text(format!("Total distance: {:.2} m\n\nTotal time: {:.2} s\n\nAverage speed: {:.2} m/s", total_distance, total_time, avg_speed));
+            self.info.set_text(format!("Total distance: {:.2} m\n\nTotal time: {:.2} s\n\nMoving time: {}\n\nElapsed time: {}\n\nAverage speed: {:.2} m/s\n\nCalories: {:.0} kcal",
+                                       total_distance, total_time, format_duration(records.total_moving_time), format_duration(records.total_elapsed_time), avg_speed, records.total_calories));
             self.info.draw(texture_id);
         }
+        for text in &mut self.profile_texts {
+            text.draw(texture_id);
+        }
 
         self.bottom_home_text.draw(texture_id);
         self.bottom_records_text.draw(texture_id);
@@ -0,0 +1,219 @@
+use std::fmt;
+use crate::tracking::auto_pause::AutoPauseConfig;
+use crate::tracking::cues::CueRules;
+use crate::tracking::config::TrackingConfig;
+use crate::tracking::outlier::OutlierConfig;
+
+/// Fewer moving speed samples than this are not enough to classify a session
+const MIN_CLASSIFY_SAMPLES: usize = 30;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ActivityType {
+    #[default]
+    Walk,
+    Run,
+    Cycle,
+    Hike,
+    /// thresholds are taken from the tracking config file as is
+    Custom,
+}
+
+impl ActivityType {
+    pub const ALL: [ActivityType; 5] = [ActivityType::Walk, ActivityType::Run, ActivityType::Cycle, ActivityType::Hike, ActivityType::Custom];
+
+    /// Following type in [`Self::ALL`], wraps around
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&a| a == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Animation shown on the active training screen
+    pub fn gif_name(&self) -> &'static str {
+        match self {
+            ActivityType::Run | ActivityType::Cycle => "running",
+            _ => "walking",
+        }
+    }
+
+    /// Built-in thresholds, `None` for [`ActivityType::Custom`]
+    pub fn default_profile(&self) -> Option<ActivityProfile> {
+        let (accuracy_ceiling, adaptive_max_ceiling) = match self {
+            ActivityType::Walk | ActivityType::Run => (5.5, 20.0),
+            ActivityType::Cycle => (8.0, 25.0),
+            ActivityType::Hike => (10.0, 30.0),
+            ActivityType::Custom => return None,
+        };
+        // (pause, resume, window) and (max speed, max acceleration, spike distance)
+        let (auto_pause, outliers, moving_speed) = match self {
+            ActivityType::Walk => ((0.3, 0.7, 8.0), (4.0, 3.0, 15.0), 0.3),
+            ActivityType::Run => ((0.6, 1.4, 6.0), (10.0, 5.0, 15.0), 0.8),
+            ActivityType::Cycle => ((1.0, 2.5, 5.0), (25.0, 6.0, 30.0), 1.5),
+            _ => ((0.2, 0.5, 12.0), (3.5, 2.0, 15.0), 0.2),
+        };
+        // pace bands only make sense when running, stops are common on a bike or a hike
+        let cues = CueRules {
+            pace: *self == ActivityType::Run,
+            auto_pause: matches!(self, ActivityType::Walk | ActivityType::Run),
+            ..CueRules::default()
+        };
+        Some(ActivityProfile {
+            accuracy_ceiling,
+            adaptive_max_ceiling,
+            auto_pause: AutoPauseConfig {
+                // hikers stop for the view on purpose
+                enabled: *self != ActivityType::Hike,
+                pause_speed: auto_pause.0,
+                resume_speed: auto_pause.1,
+                window: auto_pause.2,
+            },
+            outliers: OutlierConfig {
+                max_speed: outliers.0,
+                max_acceleration: outliers.1,
+                spike_distance: outliers.2,
+                ..OutlierConfig::default()
+            },
+            moving_speed,
+            cues,
+        })
+    }
+}
+
+impl fmt::Display for ActivityType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActivityType::Walk => write!(f, "Walk"),
+            ActivityType::Run => write!(f, "Run"),
+            ActivityType::Cycle => write!(f, "Cycle"),
+            ActivityType::Hike => write!(f, "Hike"),
+            ActivityType::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Per-activity thresholds layered over the base [`TrackingConfig`]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ActivityProfile {
+    pub accuracy_ceiling: f64,
+    pub adaptive_max_ceiling: f64,
+    pub auto_pause: AutoPauseConfig,
+    pub outliers: OutlierConfig,
+    pub moving_speed: f64,
+    pub cues: CueRules,
+}
+
+impl Default for ActivityProfile {
+    fn default() -> Self {
+        ActivityType::Walk.default_profile().unwrap()
+    }
+}
+
+impl ActivityProfile {
+    /// Thresholds `config` currently uses
+    pub fn from_config(config: &TrackingConfig) -> Self {
+        ActivityProfile {
+            accuracy_ceiling: config.accuracy_ceiling,
+            adaptive_max_ceiling: config.adaptive_max_ceiling,
+            auto_pause: config.auto_pause.clone(),
+            outliers: config.outliers.clone(),
+            moving_speed: config.moving_speed,
+            cues: config.cues.clone(),
+        }
+    }
+
+    /// Replaces the thresholds of `config`, for profiles the user edited
+    pub fn apply(&self, config: &mut TrackingConfig) {
+        config.accuracy_ceiling = self.accuracy_ceiling;
+        config.adaptive_max_ceiling = self.adaptive_max_ceiling;
+        config.auto_pause = self.auto_pause.clone();
+        config.outliers = self.outliers.clone();
+        config.moving_speed = self.moving_speed;
+        config.cues = self.cues.clone();
+    }
+
+    /// Replaces only the thresholds `config` leaves at their defaults, so values set in the
+    /// config file win over a built-in profile
+    pub fn apply_over_defaults(&self, config: &mut TrackingConfig) {
+        let defaults = TrackingConfig::default();
+        if config.accuracy_ceiling == defaults.accuracy_ceiling {
+            config.accuracy_ceiling = self.accuracy_ceiling;
+        }
+        if config.adaptive_max_ceiling == defaults.adaptive_max_ceiling {
+            config.adaptive_max_ceiling = self.adaptive_max_ceiling;
+        }
+        if config.auto_pause == defaults.auto_pause {
+            config.auto_pause = self.auto_pause.clone();
+        }
+        if config.outliers == defaults.outliers {
+            config.outliers = self.outliers.clone();
+        }
+        if config.moving_speed == defaults.moving_speed {
+            config.moving_speed = self.moving_speed;
+        }
+        if config.cues == defaults.cues {
+            config.cues = self.cues.clone();
+        }
+    }
+}
+
+/// Guesses the activity from the distribution of moving speeds (m/s) and the climb rate
+pub fn classify(speeds: &[f64], elevation_gain: f64, distance: f64) -> Option<ActivityType> {
+    if speeds.len() < MIN_CLASSIFY_SAMPLES {
+        return None;
+    }
+    let mut sorted = speeds.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    let p90 = sorted[sorted.len() * 9 / 10];
+    // meters of ascent per kilometer
+    let climb = if distance > 0.0 { elevation_gain / distance * 1000.0 } else { 0.0 };
+
+    Some(if median < 1.6 && climb > 40.0 {
+        ActivityType::Hike
+    }
+    else if median < 2.0 {
+        ActivityType::Walk
+    }
+    else if median < 4.5 && p90 < 6.0 {
+        ActivityType::Run
+    }
+    else {
+        ActivityType::Cycle
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` speeds spread evenly within `center` +- `spread`
+    fn speeds(center: f64, spread: f64, count: usize) -> Vec<f64> {
+        (0..count).map(|i| center + spread * ((i % 7) as f64 - 3.0) / 3.0).collect()
+    }
+
+    #[test]
+    fn speed_distributions() {
+        assert_eq!(classify(&speeds(1.4, 0.3, 60), 0.0, 1000.0), Some(ActivityType::Walk));
+        assert_eq!(classify(&speeds(3.0, 0.6, 60), 0.0, 1000.0), Some(ActivityType::Run));
+        assert_eq!(classify(&speeds(6.0, 1.5, 60), 0.0, 1000.0), Some(ActivityType::Cycle));
+        // running pace most of the time, but the fast part is beyond any run
+        assert_eq!(classify(&speeds(4.0, 2.5, 60), 0.0, 1000.0), Some(ActivityType::Cycle));
+    }
+
+    #[test]
+    fn too_few_samples() {
+        let walk = speeds(1.4, 0.3, MIN_CLASSIFY_SAMPLES);
+        assert_eq!(classify(&walk[1..], 0.0, 1000.0), None);
+        assert_eq!(classify(&walk, 0.0, 1000.0), Some(ActivityType::Walk));
+    }
+
+    #[test]
+    fn climb_rate_makes_a_hike() {
+        let walk = speeds(1.2, 0.3, 60);
+        assert_eq!(classify(&walk, 60.0, 1000.0), Some(ActivityType::Hike));
+        assert_eq!(classify(&walk, 30.0, 1000.0), Some(ActivityType::Walk));
+        assert_eq!(classify(&walk, 60.0, 0.0), Some(ActivityType::Walk));
+        // climbing at running pace is still a run
+        assert_eq!(classify(&speeds(3.0, 0.6, 60), 80.0, 1000.0), Some(ActivityType::Run));
+    }
+}
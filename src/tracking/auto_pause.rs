@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+/// Auto-pause thresholds. Resume speed is higher than pause speed, so standing still with a bit of
+/// GPS drift does not toggle the session back and forth.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutoPauseConfig {
+    pub enabled: bool,
+    /// session pauses when speed over the window drops below this, m/s
+    pub pause_speed: f64,
+    /// paused session resumes when speed over the window exceeds this, m/s
+    pub resume_speed: f64,
+    /// length of the sliding window, seconds
+    pub window: f64,
+}
+
+impl Default for AutoPauseConfig {
+    fn default() -> Self {
+        AutoPauseConfig {
+            enabled: false,
+            pause_speed: 0.4,
+            resume_speed: 0.9,
+            window: 6.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoPauseTransition {
+    Pause,
+    Resume,
+}
+
+/// Detects stops from the speed over a sliding time window
+#[derive(Clone, Debug, Default)]
+pub struct AutoPauseDetector {
+    /// (timestamp, north, east)
+    window: VecDeque<(f64, f64, f64)>,
+    paused: bool,
+}
+
+impl AutoPauseDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Average speed over the current window and the time span it covers
+    pub fn window_speed(&self) -> Option<(f64, f64)> {
+        let (t0, n0, e0) = *self.window.front()?;
+        let (t1, n1, e1) = *self.window.back()?;
+        let span = t1 - t0;
+        if span <= 0.0 {
+            return None;
+        }
+        Some((((n1 - n0).powi(2) + (e1 - e0).powi(2)).sqrt() / span, span))
+    }
+
+    /// Feeds a position in local meters, returns a transition if the state changed
+    pub fn update(&mut self, timestamp: f64, north: f64, east: f64, config: &AutoPauseConfig) -> Option<AutoPauseTransition> {
+        self.window.push_back((timestamp, north, east));
+        while let Some(&(t, _, _)) = self.window.front() {
+            if timestamp - t > config.window {
+                self.window.pop_front();
+            }
+            else {
+                break;
+            }
+        }
+
+        // stop must last the whole window, movement is picked up faster
+        let (speed, span) = self.window_speed()?;
+        if !self.paused && span >= config.window * 0.8 && speed < config.pause_speed {
+            self.paused = true;
+            return Some(AutoPauseTransition::Pause);
+        }
+        if self.paused && span >= config.window * 0.3 && speed > config.resume_speed {
+            self.paused = false;
+            return Some(AutoPauseTransition::Resume);
+        }
+        None
+    }
+
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.paused = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Moves north at `speed` for `seconds` from `(t, north)`, returns the transitions with their times
+    fn walk(detector: &mut AutoPauseDetector, from: &mut (f64, f64), speed: f64, seconds: usize) -> Vec<(f64, AutoPauseTransition)> {
+        let config = AutoPauseConfig { enabled: true, ..AutoPauseConfig::default() };
+        let mut transitions = Vec::new();
+        for _ in 0..seconds {
+            from.0 += 1.0;
+            from.1 += speed;
+            if let Some(transition) = detector.update(from.0, from.1, 0.0, &config) {
+                transitions.push((from.0, transition));
+            }
+        }
+        transitions
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut detector = AutoPauseDetector::new();
+        let mut at = (0.0, 0.0);
+        // slower than resuming needs, still faster than the pause threshold
+        assert!(walk(&mut detector, &mut at, 0.6, 30).is_empty());
+
+        let stop = walk(&mut detector, &mut at, 0.0, 10);
+        assert_eq!(stop.len(), 1);
+        assert_eq!(stop[0].1, AutoPauseTransition::Pause);
+        // 1.8 m over the last 6 s is below 0.4 m/s
+        assert_eq!(stop[0].0, 33.0);
+
+        // drifting between the thresholds keeps the pause
+        assert!(walk(&mut detector, &mut at, 0.6, 20).is_empty());
+        assert!(detector.is_paused());
+
+        let start = walk(&mut detector, &mut at, 2.0, 10);
+        assert_eq!(start.len(), 1);
+        assert_eq!(start[0].1, AutoPauseTransition::Resume);
+        assert!(start[0].0 <= 63.0, "{:?}", start);
+        assert!(!detector.is_paused());
+    }
+}
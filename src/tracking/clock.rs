@@ -0,0 +1,54 @@
+use crate::tracking::LocationMetric;
+
+/// Maps the monotonic timebase of location events to UTC.
+///
+/// The mapping is taken from the first fix that reports UTC and kept for the whole session,
+/// so differences of mapped UTC times always equal differences of the monotonic ones,
+/// even if the device clock is adjusted during the training.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClockMapping {
+    /// UTC seconds at monotonic time zero
+    pub offset: f64,
+}
+
+impl ClockMapping {
+    pub fn from_fix(metric: &LocationMetric) -> Option<Self> {
+        metric.utc_time.map(|utc| ClockMapping {
+            offset: utc - metric.timestamp,
+        })
+    }
+
+    pub fn utc(&self, monotonic: f64) -> f64 {
+        monotonic + self.offset
+    }
+
+    pub fn monotonic(&self, utc: f64) -> f64 {
+        utc - self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{LocationEvent, TrackingConfig, TrackingSession};
+
+    #[test]
+    fn session_keeps_first_mapping() {
+        let mut session = TrackingSession::with_config(TrackingConfig { warm_up: 0.0, ..TrackingConfig::default() });
+        let utc_start = 1_714_557_600.0;
+        for i in 0..20 {
+            let monotonic = 5000.0 + i as f64;
+            // the device clock is stepped by 2 s in the middle of the training
+            let utc = utc_start + i as f64 + if i >= 10 { 2.0 } else { 0.0 };
+            let metric = LocationMetric::new(55.75 + i as f64 * 1e-5, 37.61, 3.0, monotonic).with_utc_time(utc);
+            session.handle_event(LocationEvent::Fix(metric)).unwrap();
+        }
+
+        assert_eq!(session.utc_clock(), Some(ClockMapping { offset: utc_start - 5000.0 }));
+        assert_eq!(session.start_utc(), Some(utc_start));
+        assert_eq!(session.end_utc(), Some(utc_start + 19.0));
+        for (i, metric) in session.raw_track().enumerate() {
+            assert_eq!(metric.utc_time, Some(utc_start + i as f64));
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::fmt;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::Mutex;
+use crate::tracking::activity::{ActivityProfile, ActivityType};
+use crate::tracking::auto_pause::AutoPauseConfig;
+use crate::tracking::cues::CueRules;
+use crate::tracking::gaps::GapConfig;
+use crate::tracking::ghost::Ghost;
+use crate::tracking::goals::Goal;
+use crate::tracking::laps::LapConfig;
+use crate::tracking::outlier::OutlierConfig;
+use crate::tracking::route::{OffCourseConfig, Route};
+use crate::tracking::simplify::SimplifyConfig;
+use crate::tracking::speed::DEFAULT_SPEED_WINDOW;
+use crate::tracking::workout::WorkoutPlan;
+
+pub const CONFIG_FILE_NAME: &str = "tracking_config.json";
+
+/// Rules for accepting incoming location fixes
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// seconds after the provider came online during which fixes are ignored
+    pub warm_up: f64,
+    /// fixes with worse reported accuracy (meters) are rejected
+    pub accuracy_ceiling: f64,
+    /// follow the accuracy trend of the device instead of the fixed ceiling
+    pub adaptive_accuracy: bool,
+    /// upper bound for the adaptive ceiling, meters
+    pub adaptive_max_ceiling: f64,
+    /// adaptive ceiling is the accuracy trend multiplied by this factor
+    pub adaptive_factor: f64,
+    /// Kalman smoothing of accepted fixes, opt-in since it changes the distance totals
+    pub smoothing: bool,
+    /// rolling window for the current speed, seconds
+    pub speed_window: f64,
+    /// time counts as moving while the current speed is at least this, m/s
+    pub moving_speed: f64,
+    pub auto_pause: AutoPauseConfig,
+    pub laps: LapConfig,
+    pub outliers: OutlierConfig,
+    pub gaps: GapConfig,
+    pub simplify: SimplifyConfig,
+    /// activity picked for the next session
+    pub activity: ActivityType,
+    /// user overrides of the built-in activity profiles
+    pub profiles: HashMap<ActivityType, ActivityProfile>,
+    /// store the activity guessed from the speed distribution instead of the picked one
+    pub auto_classify: bool,
+    /// when coaching cues fire, replaced by the activity profile
+    pub cues: CueRules,
+    /// target picked for the next session.
+    /// Picks are journaled with the session but never written to the config file, see [`Self::clear_picks`].
+    pub goal: Option<Goal>,
+    /// workouts offered on the main screen
+    pub workouts: Vec<WorkoutPlan>,
+    /// structured workout picked for the next session
+    pub workout: Option<WorkoutPlan>,
+    /// past record raced in the next session
+    pub ghost: Option<Ghost>,
+    /// planned route followed in the next session
+    pub route: Option<Route>,
+    pub off_course: OffCourseConfig,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        TrackingConfig {
+            warm_up: 10.0,
+            accuracy_ceiling: 5.5,
+            adaptive_accuracy: false,
+            adaptive_max_ceiling: 20.0,
+            adaptive_factor: 1.5,
+            smoothing: false,
+            speed_window: DEFAULT_SPEED_WINDOW,
+            moving_speed: 0.5,
+            auto_pause: AutoPauseConfig::default(),
+            laps: LapConfig::default(),
+            outliers: OutlierConfig::default(),
+            gaps: GapConfig::default(),
+            simplify: SimplifyConfig::default(),
+            activity: ActivityType::default(),
+            profiles: HashMap::new(),
+            auto_classify: false,
+            cues: CueRules::default(),
+            goal: None,
+            workouts: vec![WorkoutPlan::track_intervals()],
+            workout: None,
+            ghost: None,
+            route: None,
+            off_course: OffCourseConfig::default(),
+        }
+    }
+}
+
+impl TrackingConfig {
+    /// Loads config from `dir`, falling back to defaults if there is no valid file
+    pub fn load(dir: &str) -> Self {
+        let path = format!("{}/{}", dir, CONFIG_FILE_NAME);
+        info!("Loading tracking config from file {}...", path);
+        let Ok(file) = File::open(&path) else {
+            warn!("File open failed! Using default tracking config...");
+            return Self::default();
+        };
+        match serde_json::from_reader::<_, Self>(file) {
+            Ok(mut config) => {
+                // older versions stored the picks
+                config.clear_picks();
+                config
+            }
+            Err(e) => {
+                warn!("Deserialization failed! Using default tracking config... {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Config for a new session of the picked activity, with its profile applied.
+    /// A user override replaces the thresholds, the built-in profile only fills in the defaults.
+    pub fn session_config(&self) -> TrackingConfig {
+        let mut config = self.clone();
+        if let Some(profile) = self.profiles.get(&self.activity) {
+            profile.apply(&mut config);
+        }
+        else if let Some(profile) = self.activity.default_profile() {
+            profile.apply_over_defaults(&mut config);
+        }
+        config
+    }
+
+    /// Auto-pause of the picked activity is on
+    pub fn auto_pause_enabled(&self) -> bool {
+        self.session_config().auto_pause.enabled
+    }
+
+    /// Switches auto-pause of the picked activity, the thresholds in use become a user override
+    pub fn set_auto_pause(&mut self, enabled: bool) {
+        if self.activity == ActivityType::Custom {
+            // custom activity has no profile
+            self.auto_pause.enabled = enabled;
+            return;
+        }
+        let profile = ActivityProfile::from_config(&self.session_config());
+        self.profiles.entry(self.activity).or_insert(profile).auto_pause.enabled = enabled;
+    }
+
+    /// Forgets the goal, workout, ghost and route picked for the next session
+    pub fn clear_picks(&mut self) {
+        self.goal = None;
+        self.workout = None;
+        self.ghost = None;
+        self.route = None;
+    }
+
+    /// Writes the config to `dir`, without the picks for the next session
+    pub fn save(&self, dir: &str) {
+        let path = format!("{}/{}", dir, CONFIG_FILE_NAME);
+        let Ok(mut file) = File::create(&path) else {
+            warn!("Failed to create {}!", path);
+            return;
+        };
+        let mut stored = self.clone();
+        stored.clear_picks();
+        if let Err(e) = file.write_all(serde_json::to_string(&stored).unwrap().as_bytes()) {
+            warn!("Writing tracking config failed! {:?}", e);
+        }
+    }
+}
+
+/// Why a location fix was not used by the session
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixRejection {
+    Paused,
+    /// session is auto-paused, fix is only used to detect movement
+    AutoPaused,
+    /// provider is still warming up, seconds left
+    WarmUp(f64),
+    /// reported accuracy is worse than the current ceiling
+    PoorAccuracy { accuracy: f64, ceiling: f64 },
+    /// jump from the previous fix is faster than the activity allows, m/s
+    ImplausibleSpeed { speed: f64, max: f64 },
+    /// speed-up from the previous fixes is too sudden, m/s^2
+    ImplausibleAcceleration { acceleration: f64, max: f64 },
+    /// long jump, kept back until the next fix shows whether it is a spike
+    Held,
+    /// timestamp is before the latest accepted fix of the segment, seconds back
+    OutOfOrder(f64),
+}
+
+impl fmt::Display for FixRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixRejection::Paused => write!(f, "paused"),
+            FixRejection::AutoPaused => write!(f, "auto-paused"),
+            FixRejection::WarmUp(left) => write!(f, "warming up ({:.0}s left)", left),
+            FixRejection::PoorAccuracy { accuracy, ceiling } => write!(f, "accuracy {:.1}m, max {:.1}m", accuracy, ceiling),
+            FixRejection::ImplausibleSpeed { speed, max } => write!(f, "jump at {:.1}m/s, max {:.1}m/s", speed, max),
+            FixRejection::ImplausibleAcceleration { acceleration, max } => write!(f, "speed-up {:.1}m/s2, max {:.1}m/s2", acceleration, max),
+            FixRejection::Held => write!(f, "checking jump"),
+            FixRejection::OutOfOrder(back) => write!(f, "{:.1}s older than the track", back),
+        }
+    }
+}
+
+/// Smoothing weight of a new accuracy sample in the trend
+const TREND_WEIGHT: f64 = 0.1;
+
+/// Decides whether a fix is accurate enough, following the device accuracy trend in adaptive mode
+#[derive(Clone, Debug)]
+pub struct AccuracyGate {
+    trend: Option<f64>,
+}
+
+impl AccuracyGate {
+    pub fn new() -> Self {
+        AccuracyGate {
+            trend: None,
+        }
+    }
+
+    /// Exponential moving average of reported accuracy
+    pub fn trend(&self) -> Option<f64> {
+        self.trend
+    }
+
+    pub fn ceiling(&self, config: &TrackingConfig) -> f64 {
+        match self.trend {
+            Some(trend) if config.adaptive_accuracy => (trend * config.adaptive_factor)
+                .clamp(config.accuracy_ceiling, config.adaptive_max_ceiling.max(config.accuracy_ceiling)),
+            _ => config.accuracy_ceiling,
+        }
+    }
+
+    pub fn check(&mut self, accuracy: f64, config: &TrackingConfig) -> Result<(), FixRejection> {
+        let ceiling = self.ceiling(config);
+        self.trend = Some(match self.trend {
+            Some(trend) => trend + (accuracy - trend) * TREND_WEIGHT,
+            None => accuracy,
+        });
+
+        if accuracy <= ceiling {
+            Ok(())
+        }
+        else {
+            Err(FixRejection::PoorAccuracy { accuracy, ceiling })
+        }
+    }
+}
+
+impl Default for AccuracyGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    /// Config used for new sessions, loaded on startup
+    pub static ref TRACKING_CONFIG: Mutex<TrackingConfig> = Mutex::new(TrackingConfig::default());
+}
+
+/// Replaces the config used for new sessions and persists it to `dir`
+pub fn set_tracking_config(config: TrackingConfig, dir: &str) {
+    config.save(dir);
+    *TRACKING_CONFIG.lock() = config;
+}
+
+/// Picks of the finished session don't carry over to the next one
+pub fn clear_session_picks() {
+    TRACKING_CONFIG.lock().clear_picks();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::test_dir::TestDir;
+
+    #[test]
+    fn picks_are_not_saved() {
+        let test_dir = TestDir::new("config");
+        let dir = test_dir.path();
+
+        let config = TrackingConfig {
+            warm_up: 3.0,
+            goal: Some(Goal::Distance(5000.0)),
+            workout: Some(WorkoutPlan::track_intervals()),
+            route: Route::new("park", vec![(55.75, 37.61), (55.76, 37.61)]),
+            ..TrackingConfig::default()
+        };
+        config.save(dir);
+        let loaded = TrackingConfig::load(dir);
+        assert_eq!(loaded.warm_up, 3.0);
+        assert_eq!((loaded.goal, loaded.workout, loaded.ghost, loaded.route), (None, None, None, None));
+        // the session itself keeps them
+        assert!(config.session_config().route.is_some());
+    }
+
+    #[test]
+    fn saved_thresholds_survive_the_profile() {
+        let config = TrackingConfig { accuracy_ceiling: 10.0, ..TrackingConfig::default() };
+        let session = config.session_config();
+        assert_eq!(session.accuracy_ceiling, 10.0);
+        // the rest still comes from the walking profile
+        let walk = ActivityType::Walk.default_profile().unwrap();
+        assert_eq!((session.moving_speed, session.outliers), (walk.moving_speed, walk.outliers));
+
+        // switching auto-pause keeps the saved ceiling
+        let mut config = TrackingConfig { activity: ActivityType::Run, ..config };
+        config.set_auto_pause(false);
+        let session = config.session_config();
+        assert_eq!((session.accuracy_ceiling, session.auto_pause.enabled), (10.0, false));
+
+        // an edited profile wins over the saved value
+        let mut profile = ActivityType::Walk.default_profile().unwrap();
+        profile.accuracy_ceiling = 7.0;
+        config.activity = ActivityType::Walk;
+        config.profiles.insert(ActivityType::Walk, profile);
+        assert_eq!(config.session_config().accuracy_ceiling, 7.0);
+    }
+}
@@ -0,0 +1,62 @@
+use jni::JavaVM;
+use jni::objects::{GlobalRef, JValue};
+use log::warn;
+use crate::tracking::cues::{Cue, CueSink};
+
+/// Vibration lengths in milliseconds
+const SHORT_VIBRATION: i64 = 150;
+const LONG_VIBRATION: i64 = 600;
+
+/// Speaks cues with the Android text-to-speech engine (`MainActivity.speak`)
+pub struct SpeechSink {
+    vm: JavaVM,
+    activity: GlobalRef,
+}
+
+impl SpeechSink {
+    pub fn new(vm: JavaVM, activity: GlobalRef) -> Self {
+        SpeechSink { vm, activity }
+    }
+}
+
+impl CueSink for SpeechSink {
+    fn cue(&mut self, cue: &Cue) {
+        // cues fire on the location callback thread as well as on the UI thread
+        let Ok(mut env) = self.vm.attach_current_thread() else {
+            warn!("Failed to attach thread for speech");
+            return;
+        };
+        let Ok(text) = env.new_string(cue.to_string()) else {
+            warn!("Failed to create speech string");
+            return;
+        };
+        if let Err(e) = env.call_method(self.activity.as_obj(), "speak", "(Ljava/lang/String;)V", &[(&text).into()]) {
+            warn!("Speaking cue failed: {:?}", e);
+        }
+    }
+}
+
+/// Buzzes on cues (`MainActivity.vibrate`), long for warnings
+pub struct VibrationSink {
+    vm: JavaVM,
+    activity: GlobalRef,
+}
+
+impl VibrationSink {
+    pub fn new(vm: JavaVM, activity: GlobalRef) -> Self {
+        VibrationSink { vm, activity }
+    }
+}
+
+impl CueSink for VibrationSink {
+    fn cue(&mut self, cue: &Cue) {
+        let Ok(mut env) = self.vm.attach_current_thread() else {
+            warn!("Failed to attach thread for vibration");
+            return;
+        };
+        let millis = if cue.is_warning() { LONG_VIBRATION } else { SHORT_VIBRATION };
+        if let Err(e) = env.call_method(self.activity.as_obj(), "vibrate", "(J)V", &[JValue::Long(millis)]) {
+            warn!("Vibrating failed: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use crate::tracking::cues::{Cue, CueSink};
+
+/// How long a cue stays on screen
+pub const BANNER_DURATION: Duration = Duration::from_secs(5);
+
+/// Latest cue and when it fired, read by the active training screen
+static BANNER: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Shows cues as an on-screen banner
+pub struct BannerSink;
+
+impl CueSink for BannerSink {
+    fn cue(&mut self, cue: &Cue) {
+        BANNER.lock().replace((cue.to_string(), Instant::now()));
+    }
+}
+
+/// Text of the banner, `None` once it has been shown for [`BANNER_DURATION`]
+pub fn current_banner() -> Option<String> {
+    let banner = BANNER.lock();
+    let (text, at) = banner.as_ref()?;
+    (at.elapsed() < BANNER_DURATION).then(|| text.clone())
+}
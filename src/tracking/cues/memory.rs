@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::tracking::cues::{Cue, CueSink};
+
+/// Keeps every cue, for tests and replays. Clones share the same list.
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    cues: Arc<Mutex<Vec<Cue>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cues(&self) -> Vec<Cue> {
+        self.cues.lock().clone()
+    }
+
+    pub fn clear(&self) {
+        self.cues.lock().clear();
+    }
+}
+
+impl CueSink for MemorySink {
+    fn cue(&mut self, cue: &Cue) {
+        self.cues.lock().push(cue.clone());
+    }
+}
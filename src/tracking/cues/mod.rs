@@ -0,0 +1,257 @@
+use std::fmt;
+use log::info;
+use crate::tracking::{Goal, SessionEvent, TrackingSession};
+use crate::tracking::laps::{format_duration, format_pace};
+use crate::tracking::workout::{PaceCheck, StepKind, StepTarget};
+
+pub mod android;
+pub mod banner;
+pub mod memory;
+
+/// Coaching message for the user, spoken, shown or buzzed by the [`CueSink`]s
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cue {
+    /// distance since the start in meters, pace of the split in seconds per km
+    Split { distance: f64, pace: f64 },
+    Lap { index: usize, duration: f64, pace: f64 },
+    GoalReached(Goal),
+    /// workout moved on to the next step
+    NextStep { kind: StepKind, target: StepTarget },
+    WorkoutDone,
+    TooFast,
+    TooSlow,
+    GpsLost,
+    GpsBack,
+    /// cross-track error in meters
+    OffCourse(f64),
+    BackOnCourse,
+    AutoPaused,
+    AutoResumed,
+}
+
+impl Cue {
+    /// Needs the attention of the user, sinks may make it stand out
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Cue::TooFast | Cue::TooSlow | Cue::GpsLost | Cue::OffCourse(_))
+    }
+}
+
+impl fmt::Display for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cue::Split { distance, pace } => write!(f, "{} km done, pace {}", distance / 1000.0, format_pace(*pace)),
+            Cue::Lap { index, duration, pace } => write!(f, "Lap {} in {}, pace {}", index + 1, format_duration(*duration), format_pace(*pace)),
+            Cue::GoalReached(goal) => write!(f, "Goal {} reached", goal),
+            Cue::NextStep { kind, target: StepTarget::Distance(distance) } => write!(f, "{} {:.0}m", kind, distance),
+            Cue::NextStep { kind, target: StepTarget::Duration(time) } => write!(f, "{} {}", kind, format_duration(*time)),
+            Cue::WorkoutDone => write!(f, "Workout done"),
+            Cue::TooFast => write!(f, "Too fast for this interval"),
+            Cue::TooSlow => write!(f, "Too slow for this interval"),
+            Cue::GpsLost => write!(f, "GPS lost"),
+            Cue::GpsBack => write!(f, "GPS back"),
+            Cue::OffCourse(error) => write!(f, "Off course by {:.0}m", error),
+            Cue::BackOnCourse => write!(f, "Back on course"),
+            Cue::AutoPaused => write!(f, "Auto-paused"),
+            Cue::AutoResumed => write!(f, "Resumed"),
+        }
+    }
+}
+
+/// Output of coaching cues
+pub trait CueSink: Send {
+    fn cue(&mut self, cue: &Cue);
+}
+
+/// Which cues fire and when, part of the activity profile
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CueRules {
+    pub splits: bool,
+    pub laps: bool,
+    pub goals: bool,
+    /// workout steps and the pace band of the current step
+    pub workout: bool,
+    pub pace: bool,
+    pub gps: bool,
+    /// leaving and rejoining the planned route
+    pub route: bool,
+    pub auto_pause: bool,
+    /// seconds out of the pace band before warning
+    pub pace_grace: f64,
+    /// seconds between repeated pace warnings
+    pub pace_repeat: f64,
+    /// seconds without usable fixes before the signal is reported lost
+    pub gps_lost_after: f64,
+}
+
+impl Default for CueRules {
+    fn default() -> Self {
+        CueRules {
+            splits: true,
+            laps: true,
+            goals: true,
+            workout: true,
+            pace: true,
+            gps: true,
+            route: true,
+            auto_pause: true,
+            pace_grace: 10.0,
+            pace_repeat: 30.0,
+            gps_lost_after: 15.0,
+        }
+    }
+}
+
+/// Turns session events and state into cues and hands them to the sinks
+#[derive(Default)]
+pub struct CueEngine {
+    sinks: Vec<Box<dyn CueSink>>,
+    /// direction and since when the current pace is out of the band
+    off_pace: Option<(PaceCheck, f64)>,
+    last_pace_cue: Option<f64>,
+    gps_lost: bool,
+}
+
+impl CueEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn CueSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Forgets the state of the previous training, sinks are kept
+    pub fn reset(&mut self) {
+        self.off_pace = None;
+        self.last_pace_cue = None;
+        self.gps_lost = false;
+    }
+
+    fn fire(&mut self, cue: Cue) {
+        info!("Cue: {}", cue);
+        for sink in &mut self.sinks {
+            sink.cue(&cue);
+        }
+    }
+
+    /// Cues for the session events past the first `seen` ones, then the state checks
+    pub fn update(&mut self, session: &TrackingSession, seen: usize) {
+        let rules = session.config().cues.clone();
+        for event in session.pending_events().iter().skip(seen) {
+            let cue = match event {
+                SessionEvent::Split(split) if rules.splits => Some(Cue::Split {
+                    distance: (split.index + 1) as f64 * split.distance,
+                    pace: split.pace,
+                }),
+                SessionEvent::Lap(lap) if rules.laps => Some(Cue::Lap { index: lap.index, duration: lap.duration, pace: lap.pace }),
+                SessionEvent::GoalReached(goal) if rules.goals => Some(Cue::GoalReached(*goal)),
+                SessionEvent::StepFinished(_) if rules.workout => Some(match session.workout_step() {
+                    Some(next) => Cue::NextStep { kind: next.step.kind, target: next.step.target },
+                    None => Cue::WorkoutDone,
+                }),
+                SessionEvent::OffCourse(error) if rules.route => Some(Cue::OffCourse(*error)),
+                SessionEvent::BackOnCourse if rules.route => Some(Cue::BackOnCourse),
+                SessionEvent::AutoPaused if rules.auto_pause => Some(Cue::AutoPaused),
+                SessionEvent::AutoResumed if rules.auto_pause => Some(Cue::AutoResumed),
+                _ => None,
+            };
+            if let Some(cue) = cue {
+                self.fire(cue);
+            }
+        }
+        self.check(session, &rules);
+    }
+
+    /// Checks that depend on time rather than events, also called while no fixes arrive
+    pub fn check(&mut self, session: &TrackingSession, rules: &CueRules) {
+        let Some(now) = session.estimated_now().filter(|_| !session.is_paused()) else {
+            return;
+        };
+
+        if rules.gps {
+            let lost = session.current_gap(now).is_some_and(|(duration, _)| duration >= rules.gps_lost_after);
+            if lost != self.gps_lost {
+                self.gps_lost = lost;
+                self.fire(if lost { Cue::GpsLost } else { Cue::GpsBack });
+            }
+        }
+
+        let pace = session.current_pace();
+        let band = session.workout_step().and_then(|progress| progress.step.pace);
+        let check = band.filter(|_| rules.pace && pace > 0.0 && !self.gps_lost).map(|band| band.check(pace));
+        match check {
+            Some(check @ (PaceCheck::TooFast | PaceCheck::TooSlow)) => {
+                let since = match self.off_pace {
+                    Some((previous, since)) if previous == check => since,
+                    _ => now,
+                };
+                self.off_pace = Some((check, since));
+                let repeat_due = self.last_pace_cue.is_none_or(|at| now - at >= rules.pace_repeat);
+                if now - since >= rules.pace_grace && repeat_due {
+                    self.last_pace_cue = Some(now);
+                    self.fire(if check == PaceCheck::TooFast { Cue::TooFast } else { Cue::TooSlow });
+                }
+            }
+            _ => {
+                self.off_pace = None;
+                self.last_pace_cue = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{Recorder, TrackingConfig, WorkoutPlan};
+    use crate::tracking::cues::memory::MemorySink;
+    use crate::tracking::sources::synthetic::SyntheticSource;
+    use crate::tracking::workout::WorkoutStep;
+
+    fn start(config: TrackingConfig) -> (Recorder, MemorySink) {
+        let mut recorder = Recorder::new();
+        let sink = MemorySink::new();
+        recorder.add_cue_sink(Box::new(sink.clone()));
+        recorder.start(config);
+        (recorder, sink)
+    }
+
+    #[test]
+    fn split_cues() {
+        let config = TrackingConfig { warm_up: 0.0, smoothing: false, ..TrackingConfig::default() };
+        let (mut recorder, sink) = start(config);
+        let mut source = SyntheticSource::new((55.75, 37.61), 3.0, 0.0, 700);
+        recorder.drain(&mut source);
+
+        let cues = sink.cues();
+        let splits: Vec<_> = cues.iter().filter(|cue| matches!(cue, Cue::Split { .. })).collect();
+        assert_eq!(splits.len(), 2, "{:?}", cues);
+        assert_eq!(splits[0].to_string(), "1 km done, pace 5:33");
+
+        // same run with split cues switched off
+        let mut config = TrackingConfig { warm_up: 0.0, smoothing: false, ..TrackingConfig::default() };
+        config.cues.splits = false;
+        let (mut recorder, sink) = start(config);
+        recorder.drain(&mut SyntheticSource::new((55.75, 37.61), 3.0, 0.0, 700));
+        assert!(sink.cues().iter().all(|cue| !matches!(cue, Cue::Split { .. })));
+    }
+
+    #[test]
+    fn pace_warning_after_grace() {
+        let plan = WorkoutPlan::new("tempo")
+            .step(WorkoutStep::new(StepKind::Work, StepTarget::Distance(5000.0)).pace(200.0, 250.0));
+        let config = TrackingConfig { warm_up: 0.0, smoothing: false, workout: Some(plan), ..TrackingConfig::default() };
+        let (mut recorder, sink) = start(config);
+
+        // 2 m/s is 8:20/km, way too slow for the band
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 120);
+        recorder.drain(&mut source);
+
+        let warnings: Vec<_> = sink.cues().into_iter().filter(Cue::is_warning).collect();
+        assert!(!warnings.is_empty());
+        assert!(warnings.iter().all(|cue| *cue == Cue::TooSlow));
+        // first after the grace time, then every 30 s
+        assert!(warnings.len() <= 4, "{:?}", warnings);
+    }
+}
@@ -0,0 +1,103 @@
+/// Altitude changes smaller than this are treated as noise, meters
+const MIN_STEP: f64 = 3.0;
+/// Fixes with worse vertical accuracy are ignored, meters
+const MAX_VERTICAL_ACCURACY: f64 = 15.0;
+
+/// Accumulates total ascent and descent from noisy altitude readings.
+///
+/// Altitude is compared to a reference point that only moves once the change exceeds the
+/// noise threshold, so jitter around a constant altitude adds nothing.
+#[derive(Clone, Debug, Default)]
+pub struct ElevationTracker {
+    reference: Option<f64>,
+    gain: f64,
+    loss: f64,
+}
+
+impl ElevationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, altitude: f64, vertical_accuracy: Option<f64>) {
+        if vertical_accuracy.is_some_and(|acc| acc > MAX_VERTICAL_ACCURACY) {
+            return;
+        }
+        let threshold = vertical_accuracy.map_or(MIN_STEP, |acc| acc.max(MIN_STEP));
+
+        let Some(reference) = self.reference else {
+            self.reference = Some(altitude);
+            return;
+        };
+        let diff = altitude - reference;
+        if diff.abs() >= threshold {
+            if diff > 0.0 {
+                self.gain += diff;
+            }
+            else {
+                self.loss -= diff;
+            }
+            self.reference = Some(altitude);
+        }
+    }
+
+    /// Total ascent, meters
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Total descent, meters, positive
+    pub fn loss(&self) -> f64 {
+        self.loss
+    }
+
+    /// Latest altitude used as reference
+    pub fn altitude(&self) -> Option<f64> {
+        self.reference
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Repeating noise within +-2 m
+    const NOISE: [f64; 6] = [0.0, 2.0, -1.5, 1.0, -2.0, 0.5];
+
+    #[test]
+    fn noise_adds_nothing() {
+        let mut tracker = ElevationTracker::new();
+        for i in 0..60 {
+            tracker.update(100.0 + NOISE[i % NOISE.len()], None);
+        }
+        assert_eq!((tracker.gain(), tracker.loss()), (0.0, 0.0));
+        assert_eq!(tracker.altitude(), Some(100.0));
+    }
+
+    #[test]
+    fn noisy_climb_and_descent() {
+        let mut tracker = ElevationTracker::new();
+        // 30 m up and 20 m down, half a meter per fix
+        let profile = (0..=60).map(|i| 100.0 + i as f64 * 0.5).chain((1..=40).map(|i| 130.0 - i as f64 * 0.5));
+        for (i, altitude) in profile.enumerate() {
+            tracker.update(altitude + NOISE[i % NOISE.len()], None);
+        }
+        assert!((tracker.gain() - 30.0).abs() <= MIN_STEP, "{}", tracker.gain());
+        assert!((tracker.loss() - 20.0).abs() <= MIN_STEP, "{}", tracker.loss());
+    }
+
+    #[test]
+    fn vertical_accuracy_filters() {
+        let mut tracker = ElevationTracker::new();
+        tracker.update(100.0, Some(2.0));
+        // too inaccurate to use at all
+        tracker.update(200.0, Some(20.0));
+        assert_eq!(tracker.altitude(), Some(100.0));
+        // within the reported accuracy, treated as noise
+        tracker.update(106.0, Some(8.0));
+        assert_eq!(tracker.gain(), 0.0);
+        // the same step from an accurate fix counts
+        tracker.update(106.0, Some(2.0));
+        assert_eq!(tracker.gain(), 6.0);
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::Write;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::Mutex;
+use crate::tracking::activity::ActivityType;
+
+pub const PROFILE_FILE_NAME: &str = "user_profile.json";
+
+/// Share of metabolic energy turned into lifting the body uphill
+const CLIMB_EFFICIENCY: f64 = 0.25;
+const JOULES_PER_KCAL: f64 = 4184.0;
+const GRAVITY: f64 = 9.81;
+
+/// (speed m/s, MET) points from the Compendium of Physical Activities, interpolated in between
+const WALK_METS: [(f64, f64); 5] = [(0.9, 2.0), (1.2, 2.8), (1.4, 3.5), (1.8, 5.0), (2.2, 7.0)];
+const RUN_METS: [(f64, f64); 7] = [(2.2, 8.0), (2.7, 9.0), (3.0, 9.8), (3.3, 10.5), (3.9, 11.5), (4.5, 12.8), (5.4, 14.5)];
+const CYCLE_METS: [(f64, f64); 5] = [(4.0, 4.0), (5.0, 6.8), (6.0, 8.0), (7.0, 10.0), (8.0, 12.0)];
+const HIKE_METS: [(f64, f64); 3] = [(0.8, 4.5), (1.2, 5.3), (1.6, 6.5)];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Sex {
+    Male,
+    Female,
+    #[default]
+    Unspecified,
+}
+
+impl Sex {
+    /// Following option, wraps around
+    pub fn next(self) -> Self {
+        match self {
+            Sex::Male => Sex::Female,
+            Sex::Female => Sex::Unspecified,
+            Sex::Unspecified => Sex::Male,
+        }
+    }
+}
+
+impl std::fmt::Display for Sex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sex::Male => write!(f, "male"),
+            Sex::Female => write!(f, "female"),
+            Sex::Unspecified => write!(f, "unspecified"),
+        }
+    }
+}
+
+/// Body measurements used for the energy estimate
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct UserProfile {
+    /// kilograms
+    pub weight: f64,
+    /// centimeters
+    pub height: f64,
+    /// years
+    pub age: f64,
+    pub sex: Sex,
+}
+
+impl Default for UserProfile {
+    fn default() -> Self {
+        UserProfile {
+            weight: 70.0,
+            height: 175.0,
+            age: 30.0,
+            sex: Sex::Unspecified,
+        }
+    }
+}
+
+impl UserProfile {
+    /// Loads the profile from `dir`, falling back to defaults if there is no valid file
+    pub fn load(dir: &str) -> Self {
+        let path = format!("{}/{}", dir, PROFILE_FILE_NAME);
+        info!("Loading user profile from file {}...", path);
+        let Ok(file) = File::open(&path) else {
+            warn!("File open failed! Using default user profile...");
+            return Self::default();
+        };
+        match serde_json::from_reader(file) {
+            Ok(profile) => profile,
+            Err(e) => {
+                warn!("Deserialization failed! Using default user profile... {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, dir: &str) {
+        let path = format!("{}/{}", dir, PROFILE_FILE_NAME);
+        let Ok(mut file) = File::create(&path) else {
+            warn!("Failed to create {}!", path);
+            return;
+        };
+        if let Err(e) = file.write_all(serde_json::to_string(self).unwrap().as_bytes()) {
+            warn!("Writing user profile failed! {:?}", e);
+        }
+    }
+
+    /// Resting energy expenditure by Mifflin-St Jeor, kcal per second
+    pub fn resting_rate(&self) -> f64 {
+        let sex_term = match self.sex {
+            Sex::Male => 5.0,
+            Sex::Female => -161.0,
+            Sex::Unspecified => -78.0,
+        };
+        let per_day = 10.0 * self.weight + 6.25 * self.height - 5.0 * self.age + sex_term;
+        per_day.max(0.0) / 86400.0
+    }
+
+    /// Energy spent over `duration` seconds with the given effort above rest and ascent in meters, kcal
+    pub fn calories(&self, net_met_seconds: f64, duration: f64, elevation_gain: f64) -> f64 {
+        // one MET is about 1 kcal per kilogram per hour
+        let active = net_met_seconds * self.weight / 3600.0;
+        let resting = self.resting_rate() * duration;
+        let climb = elevation_gain * self.weight * GRAVITY / CLIMB_EFFICIENCY / JOULES_PER_KCAL;
+        active + resting + climb
+    }
+}
+
+fn interpolate(table: &[(f64, f64)], speed: f64) -> f64 {
+    if !speed.is_finite() {
+        // missing speed, count it as rest
+        return 1.0;
+    }
+    let (first, last) = (table[0], table[table.len() - 1]);
+    if speed <= first.0 {
+        // slower than the table covers, scale down towards rest
+        return 1.0 + (first.1 - 1.0) * (speed / first.0).max(0.0);
+    }
+    if speed >= last.0 {
+        return last.1;
+    }
+    let i = table.iter().position(|&(s, _)| s > speed).unwrap();
+    let ((s0, m0), (s1, m1)) = (table[i - 1], table[i]);
+    m0 + (m1 - m0) * (speed - s0) / (s1 - s0)
+}
+
+/// Metabolic equivalent of `activity` at `speed` m/s
+pub fn met(activity: ActivityType, speed: f64) -> f64 {
+    match activity {
+        ActivityType::Walk => interpolate(&WALK_METS, speed),
+        ActivityType::Run => interpolate(&RUN_METS, speed),
+        ActivityType::Cycle => interpolate(&CYCLE_METS, speed),
+        ActivityType::Hike => interpolate(&HIKE_METS, speed),
+        ActivityType::Custom if speed < WALK_METS[WALK_METS.len() - 1].0 => interpolate(&WALK_METS, speed),
+        ActivityType::Custom => interpolate(&RUN_METS, speed),
+    }
+}
+
+/// Effort above rest accumulated over the speed profile of a session, independent of the user.
+/// Kept for every activity since the one stored with the session may only be known at the end
+#[derive(Clone, Debug, Default)]
+pub struct EffortAccumulator {
+    net_met_seconds: [f64; ActivityType::ALL.len()],
+}
+
+impl EffortAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `duration` seconds spent at `speed` m/s
+    pub fn update(&mut self, speed: f64, duration: f64) {
+        for (sum, &activity) in self.net_met_seconds.iter_mut().zip(ActivityType::ALL.iter()) {
+            *sum += (met(activity, speed) - 1.0).max(0.0) * duration;
+        }
+    }
+
+    /// Sum of (MET - 1) over time for `activity`, MET-seconds
+    pub fn net_met_seconds(&self, activity: ActivityType) -> f64 {
+        let i = ActivityType::ALL.iter().position(|&a| a == activity).unwrap();
+        self.net_met_seconds[i]
+    }
+}
+
+lazy_static! {
+    /// Profile of the phone owner, loaded on startup
+    pub static ref USER_PROFILE: Mutex<UserProfile> = Mutex::new(UserProfile::default());
+}
+
+/// Replaces the profile of the phone owner and persists it to `dir`
+pub fn set_user_profile(profile: UserProfile, dir: &str) {
+    profile.save(dir);
+    *USER_PROFILE.lock() = profile;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn met_table_lookup() {
+        // below the table, halfway to rest
+        assert!((met(ActivityType::Walk, 0.45) - 1.5).abs() < 1e-9);
+        assert_eq!(met(ActivityType::Walk, 0.0), 1.0);
+        // between 2.7 m/s (9.0) and 3.0 m/s (9.8)
+        assert!((met(ActivityType::Run, 2.85) - 9.4).abs() < 1e-9);
+        assert_eq!(met(ActivityType::Run, 3.0), 9.8);
+        // above the table
+        assert_eq!(met(ActivityType::Cycle, 15.0), 12.0);
+        assert_eq!(met(ActivityType::Custom, 3.0), 9.8);
+        assert_eq!(met(ActivityType::Walk, f64::NAN), 1.0);
+        assert_eq!(met(ActivityType::Run, f64::INFINITY), 1.0);
+    }
+
+    #[test]
+    fn nan_speed_adds_no_effort() {
+        let mut effort = EffortAccumulator::new();
+        effort.update(f64::NAN, 5.0);
+        effort.update(1.4, 10.0);
+        assert!((effort.net_met_seconds(ActivityType::Walk) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mifflin_st_jeor() {
+        let male = UserProfile { sex: Sex::Male, ..UserProfile::default() };
+        // 10 * 70 + 6.25 * 175 - 5 * 30 + 5 = 1648.75 kcal a day
+        assert!((male.resting_rate() * 86400.0 - 1648.75).abs() < 1e-9);
+        let female = UserProfile { weight: 60.0, height: 165.0, age: 40.0, sex: Sex::Female };
+        assert!((female.resting_rate() * 86400.0 - 1270.25).abs() < 1e-9);
+
+        // an hour at 3 MET with 100 m of ascent: 140 active, 68.70 resting, 65.65 climbing
+        let calories = male.calories(2.0 * 3600.0, 3600.0, 100.0);
+        assert!((calories - 274.348).abs() < 1e-3, "{}", calories);
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+use crate::tracking::config::FixRejection;
+
+/// What to do with the time and distance between the fixes around a signal loss
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GapPolicy {
+    /// count the straight line between the fixes around the gap
+    Bridge,
+    /// count nothing, the track continues in a new segment
+    Exclude,
+    /// count the straight line and fill it with evenly spaced points, so speed and splits change gradually
+    Interpolate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GapReason {
+    /// provider stopped reporting fixes
+    NoFixes,
+    /// fixes arrived but were not accurate enough
+    PoorAccuracy,
+    /// fixes arrived but were rejected as implausible jumps
+    Outliers,
+    /// location provider was turned off
+    ProviderDisabled,
+}
+
+impl fmt::Display for GapReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GapReason::NoFixes => write!(f, "no fixes"),
+            GapReason::PoorAccuracy => write!(f, "bad accuracy"),
+            GapReason::Outliers => write!(f, "bad fixes"),
+            GapReason::ProviderDisabled => write!(f, "GPS off"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GapConfig {
+    /// pause between accepted fixes that counts as a signal loss, seconds
+    pub threshold: f64,
+    /// policy for gaps up to `exclude_after`
+    pub policy: GapPolicy,
+    /// longer gaps, and gaps with the provider turned off, are always excluded, seconds
+    pub exclude_after: f64,
+    /// spacing of interpolated points, seconds
+    pub interpolation_step: f64,
+}
+
+impl Default for GapConfig {
+    fn default() -> Self {
+        GapConfig {
+            threshold: 5.0,
+            policy: GapPolicy::Interpolate,
+            exclude_after: 120.0,
+            interpolation_step: 1.0,
+        }
+    }
+}
+
+impl GapConfig {
+    pub fn policy_for(&self, duration: f64, reason: GapReason) -> GapPolicy {
+        if reason == GapReason::ProviderDisabled || duration > self.exclude_after {
+            GapPolicy::Exclude
+        }
+        else {
+            self.policy
+        }
+    }
+}
+
+/// Signal loss between two accepted fixes of a session
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Gap {
+    /// timestamp of the last fix before the gap
+    pub start: f64,
+    /// timestamp of the first fix after the gap
+    pub end: f64,
+    pub reason: GapReason,
+    pub policy: GapPolicy,
+}
+
+impl Gap {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Remembers why no fix was accepted since the last accepted one
+#[derive(Clone, Debug, Default)]
+pub struct GapReasonTracker {
+    reason: Option<GapReason>,
+}
+
+impl GapReasonTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rejected(&mut self, rejection: FixRejection) {
+        let reason = match rejection {
+            FixRejection::PoorAccuracy { .. } => GapReason::PoorAccuracy,
+            FixRejection::ImplausibleSpeed { .. } | FixRejection::ImplausibleAcceleration { .. } => GapReason::Outliers,
+            _ => return,
+        };
+        // provider being off explains the gap better than anything seen around it
+        if self.reason != Some(GapReason::ProviderDisabled) {
+            self.reason = Some(reason);
+        }
+    }
+
+    pub fn provider_disabled(&mut self) {
+        self.reason = Some(GapReason::ProviderDisabled);
+    }
+
+    pub fn reason(&self) -> GapReason {
+        self.reason.unwrap_or(GapReason::NoFixes)
+    }
+
+    /// Called for every accepted fix
+    pub fn reset(&mut self) {
+        self.reason = None;
+    }
+}
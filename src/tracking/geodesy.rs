@@ -0,0 +1,152 @@
+//! Distances on the Earth surface.
+//!
+//! [`haversine`] treats the Earth as a sphere and is good enough for short steps, [`vincenty`]
+//! solves the inverse geodesic problem on the WGS-84 ellipsoid and is accurate to a fraction of a millimeter.
+
+/// Mean Earth radius in meters
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// WGS-84 semi-major axis in meters
+pub const WGS84_A: f64 = 6_378_137.0;
+/// WGS-84 flattening
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Meters per degree of latitude, used by the local display projection
+pub const METERS_PER_DEGREE: f64 = 111_319.5;
+
+/// Great-circle distance in meters between two points given in degrees
+pub fn haversine(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let phi1 = lat1.to_radians();
+    let phi2 = lat2.to_radians();
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
+/// Ellipsoidal distance in meters between two points given in degrees (Vincenty inverse formula).
+///
+/// Returns `None` if the iteration does not converge, which only happens for nearly antipodal points.
+pub fn vincenty(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Option<f64> {
+    let b = WGS84_A * (1.0 - WGS84_F);
+
+    let l = (lon2 - lon1).to_radians();
+    let u1 = ((1.0 - WGS84_F) * lat1.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * lat2.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2)).sqrt();
+        if sin_sigma == 0.0 {
+            // coincident points
+            return Some(0.0);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        // equatorial line has cos_sq_alpha == 0
+        let cos_2sigma_m = if cos_sq_alpha != 0.0 {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha
+        } else {
+            0.0
+        };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+
+        let lambda_prev = lambda;
+        lambda = l + (1.0 - c) * WGS84_F * sin_alpha
+            * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - lambda_prev).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+            let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b * sin_sigma * (cos_2sigma_m + big_b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                - big_b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma) * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+
+            return Some(b * big_a * (sigma - delta_sigma));
+        }
+    }
+
+    None
+}
+
+/// Ellipsoidal distance with a spherical fallback for the rare non-converging cases
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    vincenty(lat1, lon1, lat2, lon2).unwrap_or_else(|| haversine(lat1, lon1, lat2, lon2))
+}
+
+/// Flat projection around a fixed origin, only meant for drawing the track on screen
+#[derive(Clone, Copy, Debug)]
+pub struct LocalProjection {
+    origin_lat: f64,
+    origin_lon: f64,
+    lon_scale: f64,
+}
+
+impl LocalProjection {
+    pub fn new(origin_lat: f64, origin_lon: f64) -> Self {
+        LocalProjection {
+            origin_lat,
+            origin_lon,
+            lon_scale: METERS_PER_DEGREE * origin_lat.to_radians().cos(),
+        }
+    }
+
+    /// (north, east) offset in meters from the origin
+    pub fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        ((lat - self.origin_lat) * METERS_PER_DEGREE, (lon - self.origin_lon) * self.lon_scale)
+    }
+
+    /// Inverse of [`LocalProjection::project`]
+    pub fn unproject(&self, north: f64, east: f64) -> (f64, f64) {
+        (self.origin_lat + north / METERS_PER_DEGREE, self.origin_lon + east / self.lon_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degrees(d: f64, m: f64, s: f64) -> f64 {
+        d.signum() * (d.abs() + m / 60.0 + s / 3600.0)
+    }
+
+    #[test]
+    fn vincenty_reference_lines() {
+        // Flinders Peak to Buninyong, Geoscience Australia
+        let flinders = (degrees(-37.0, 57.0, 3.72030), degrees(144.0, 25.0, 29.52440));
+        let buninyong = (degrees(-37.0, 39.0, 10.15610), degrees(143.0, 55.0, 35.38390));
+        let d = vincenty(flinders.0, flinders.1, buninyong.0, buninyong.1).unwrap();
+        assert!((d - 54_972.271).abs() < 0.001, "{}", d);
+
+        // a quarter of the equator and a quarter of a meridian
+        assert!((vincenty(0.0, 0.0, 0.0, 90.0).unwrap() - 10_018_754.171).abs() < 0.001);
+        assert!((vincenty(0.0, 0.0, 90.0, 0.0).unwrap() - 10_001_965.729).abs() < 0.001);
+        assert_eq!(vincenty(55.75, 37.61, 55.75, 37.61), Some(0.0));
+    }
+
+    #[test]
+    fn antipodal_fallback() {
+        assert_eq!(vincenty(0.0, 0.0, 0.5, 179.7), None);
+        let d = distance(0.0, 0.0, 0.5, 179.7);
+        assert_eq!(d, haversine(0.0, 0.0, 0.5, 179.7));
+        assert!((d - 20_000_000.0).abs() < 50_000.0, "{}", d);
+    }
+
+    #[test]
+    fn haversine_close_to_vincenty() {
+        // a 1 km step and a long line, the sphere is off by well under a percent
+        for (lat1, lon1, lat2, lon2) in [(55.75, 37.61, 55.759, 37.61), (55.75, 37.61, 59.94, 30.31), (-33.86, 151.21, 51.51, -0.13)] {
+            let ellipsoid = vincenty(lat1, lon1, lat2, lon2).unwrap();
+            let sphere = haversine(lat1, lon1, lat2, lon2);
+            assert!((sphere - ellipsoid).abs() / ellipsoid < 0.005, "{} vs {}", sphere, ellipsoid);
+        }
+    }
+}
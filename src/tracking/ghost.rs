@@ -0,0 +1,161 @@
+use std::fmt;
+use crate::tracking::LocationMetric;
+use crate::tracking::geodesy::distance;
+use crate::tracking::laps::format_duration;
+
+/// Distance over training time of a past session, pauses and gaps between segments excluded
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GhostCurve {
+    /// (training time in seconds, distance in meters), both non-decreasing
+    points: Vec<(f64, f64)>,
+}
+
+impl GhostCurve {
+    /// Builds the curve from stored track segments.
+    /// Returns `None` if the track has no two fixes to measure between.
+    pub fn from_track(segments: &[Vec<LocationMetric>]) -> Option<Self> {
+        let mut points = vec![(0.0, 0.0)];
+        let (mut time, mut covered) = (0.0, 0.0);
+        for segment in segments {
+            for pair in segment.windows(2) {
+                let (a, b) = (&pair[0], &pair[1]);
+                time += (b.timestamp - a.timestamp).max(0.0);
+                covered += distance(a.latitude, a.longitude, b.latitude, b.longitude);
+                points.push((time, covered));
+            }
+        }
+        (points.len() > 1 && time > 0.0).then_some(GhostCurve { points })
+    }
+
+    /// Stretches the curve to end at the given totals.
+    /// A simplified track is shorter than what was recorded, the record totals are exact.
+    pub fn scaled(mut self, total_distance: f64, total_time: f64) -> Self {
+        let (time, covered) = self.end();
+        let time_factor = if time > 0.0 && total_time > 0.0 { total_time / time } else { 1.0 };
+        let distance_factor = if covered > 0.0 && total_distance > 0.0 { total_distance / covered } else { 1.0 };
+        for point in &mut self.points {
+            point.0 *= time_factor;
+            point.1 *= distance_factor;
+        }
+        self
+    }
+
+    /// (training time, distance) at the end of the session
+    pub fn end(&self) -> (f64, f64) {
+        self.points.last().copied().unwrap_or((0.0, 0.0))
+    }
+
+    /// Distance the ghost had covered after `time` seconds, its total once it has finished
+    pub fn distance_at(&self, time: f64) -> f64 {
+        let i = self.points.partition_point(|&(t, _)| t < time);
+        match (i.checked_sub(1).map(|i| self.points[i]), self.points.get(i)) {
+            (Some((t0, d0)), Some(&(t1, d1))) if t1 > t0 => d0 + (d1 - d0) * (time - t0) / (t1 - t0),
+            (_, Some(&(_, d))) => d,
+            _ => self.end().1,
+        }
+    }
+
+    /// Training time when the ghost reached `covered` meters, `None` past its total distance
+    pub fn time_at(&self, covered: f64) -> Option<f64> {
+        let i = self.points.partition_point(|&(_, d)| d < covered);
+        match (i.checked_sub(1).map(|i| self.points[i]), self.points.get(i)) {
+            (Some((t0, d0)), Some(&(t1, d1))) if d1 > d0 => Some(t0 + (t1 - t0) * (covered - d0) / (d1 - d0)),
+            (_, Some(&(t, _))) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Where a session at `covered` meters after `time` seconds stands against the ghost
+    pub fn compare(&self, covered: f64, time: f64) -> GhostGap {
+        GhostGap {
+            distance_ahead: covered - self.distance_at(time),
+            time_ahead: self.time_at(covered).map(|ghost_time| ghost_time - time),
+        }
+    }
+}
+
+/// Past record raced in the next session
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Ghost {
+    /// end timestamp of the record, identifies it in the records list
+    pub record: f64,
+    pub curve: GhostCurve,
+}
+
+/// Live lead over the ghost, negative values mean behind
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GhostGap {
+    /// meters at the same training time
+    pub distance_ahead: f64,
+    /// seconds at the same distance, `None` once past the distance of the ghost
+    pub time_ahead: Option<f64>,
+}
+
+impl fmt::Display for GhostGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.distance_ahead >= 0.0 { "ahead" } else { "behind" };
+        write!(f, "Ghost: {:.0}m {}", self.distance_ahead.abs(), side)?;
+        match self.time_ahead {
+            Some(time) if time >= 0.0 => write!(f, ", {} faster", format_duration(time)),
+            Some(time) => write!(f, ", {} slower", format_duration(-time)),
+            None => write!(f, ", ghost finished"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{LocationSource, TrackingConfig, TrackingSession};
+    use crate::tracking::sources::synthetic::SyntheticSource;
+
+    fn curve() -> GhostCurve {
+        // 100 m in 40 s, standing 20 s, then 100 m in 20 s
+        GhostCurve { points: vec![(0.0, 0.0), (40.0, 100.0), (60.0, 100.0), (80.0, 200.0)] }
+    }
+
+    #[test]
+    fn interpolation() {
+        let curve = curve();
+        assert_eq!(curve.distance_at(20.0), 50.0);
+        assert_eq!(curve.distance_at(50.0), 100.0);
+        assert_eq!(curve.distance_at(70.0), 150.0);
+        assert_eq!(curve.distance_at(500.0), 200.0);
+
+        assert_eq!(curve.time_at(50.0), Some(20.0));
+        // the first time the distance was reached
+        assert_eq!(curve.time_at(100.0), Some(40.0));
+        assert_eq!(curve.time_at(150.0), Some(70.0));
+        assert_eq!(curve.time_at(201.0), None);
+
+        let gap = curve.compare(60.0, 20.0);
+        assert_eq!(gap.distance_ahead, 10.0);
+        assert_eq!(gap.time_ahead, Some(4.0));
+        assert_eq!(gap.to_string(), "Ghost: 10m ahead, 00:04 faster");
+    }
+
+    #[test]
+    fn race_against_stored_track() {
+        let config = TrackingConfig { warm_up: 0.0, smoothing: false, ..TrackingConfig::default() };
+        let mut past = TrackingSession::with_config(config.clone());
+        let mut source = SyntheticSource::new((55.75, 37.61), 3.0, 30.0, 301);
+        while let Some(event) = source.next_event() {
+            let _ = past.handle_event(event);
+        }
+        let curve = GhostCurve::from_track(&past.storage_track()).unwrap().scaled(past.total_distance(), past.total_time());
+        assert!((curve.end().1 - 900.0).abs() < 1.0);
+        assert_eq!(curve.end().0, 300.0);
+
+        // a bit faster than the ghost
+        let ghost = Ghost { record: 0.0, curve };
+        let mut session = TrackingSession::with_config(TrackingConfig { ghost: Some(ghost), ..config });
+        let mut source = SyntheticSource::new((55.75, 37.61), 3.3, 30.0, 101);
+        while let Some(event) = source.next_event() {
+            let _ = session.handle_event(event);
+        }
+        let gap = session.ghost_gap().unwrap();
+        assert!((gap.distance_ahead - 30.0).abs() < 1.0, "{:?}", gap);
+        // 330 m took the ghost 110 s
+        assert!((gap.time_ahead.unwrap() - 10.0).abs() < 0.5, "{:?}", gap);
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use crate::tracking::laps::{format_duration, format_pace};
+
+/// Target picked for a training. Times are training time, pauses excluded.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Goal {
+    /// meters
+    Distance(f64),
+    /// seconds
+    Duration(f64),
+    /// cover `distance` meters in at most `time` seconds
+    TimedDistance { distance: f64, time: f64 },
+}
+
+impl Goal {
+    /// Goals offered on the main screen, `None` is training without a goal
+    pub const PRESETS: [Option<Goal>; 7] = [
+        None,
+        Some(Goal::Distance(5000.0)),
+        Some(Goal::Distance(10000.0)),
+        Some(Goal::Duration(1800.0)),
+        Some(Goal::Duration(3600.0)),
+        Some(Goal::TimedDistance { distance: 5000.0, time: 1800.0 }),
+        Some(Goal::TimedDistance { distance: 10000.0, time: 3600.0 }),
+    ];
+
+    /// Preset following `goal` on the main screen
+    pub fn next_preset(goal: Option<Goal>) -> Option<Goal> {
+        let i = Self::PRESETS.iter().position(|&preset| preset == goal);
+        Self::PRESETS[i.map_or(0, |i| (i + 1) % Self::PRESETS.len())]
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::Distance(distance) => write!(f, "{:.2} km", distance / 1000.0),
+            Goal::Duration(time) => write!(f, "{}", format_duration(*time)),
+            Goal::TimedDistance { distance, time } => write!(f, "{:.2} km in {}", distance / 1000.0, format_duration(*time)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoalStatus {
+    InProgress,
+    Reached,
+    /// time ran out before the distance was covered
+    Missed,
+}
+
+/// Where the training stands against its goal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub status: GoalStatus,
+    /// meters or seconds, same unit as the target
+    pub done: f64,
+    pub target: f64,
+    /// pace needed for the rest of a timed distance, seconds per km
+    pub required_pace: Option<f64>,
+}
+
+impl GoalProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.target > 0.0 { (self.done / self.target).min(1.0) } else { 1.0 }
+    }
+}
+
+impl fmt::Display for GoalProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.status, self.goal) {
+            (GoalStatus::Reached, _) => write!(f, "reached!"),
+            (GoalStatus::Missed, _) => write!(f, "missed"),
+            (_, Goal::Duration(_)) => write!(f, "{} / {}", format_duration(self.done), format_duration(self.target)),
+            _ => {
+                write!(f, "{:.2} / {:.2} km", self.done / 1000.0, self.target / 1000.0)?;
+                match self.required_pace {
+                    Some(pace) => write!(f, ", need {}/km", format_pace(pace)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Follows the progress of a session towards its goal
+#[derive(Clone, Debug)]
+pub struct GoalTracker {
+    goal: Goal,
+    /// training time when the target distance or duration was covered
+    completed_at: Option<f64>,
+    /// (distance, time) of the previous update, to find the exact crossing
+    previous: (f64, f64),
+}
+
+impl GoalTracker {
+    pub fn new(goal: Goal) -> Self {
+        GoalTracker {
+            goal,
+            completed_at: None,
+            previous: (0.0, 0.0),
+        }
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    /// Takes the session totals after an accepted fix. Returns true once, when the goal is reached.
+    pub fn update(&mut self, distance: f64, time: f64) -> bool {
+        if self.completed_at.is_some() {
+            return false;
+        }
+        let (prev_distance, prev_time) = self.previous;
+        self.previous = (distance, time);
+
+        self.completed_at = match self.goal {
+            Goal::Distance(target) | Goal::TimedDistance { distance: target, .. } if distance >= target => {
+                let f = if distance > prev_distance { (target - prev_distance) / (distance - prev_distance) } else { 1.0 };
+                Some(prev_time + (time - prev_time) * f.clamp(0.0, 1.0))
+            }
+            Goal::Duration(target) if time >= target => Some(target),
+            _ => None,
+        };
+        self.is_met()
+    }
+
+    pub fn is_met(&self) -> bool {
+        match (self.goal, self.completed_at) {
+            (_, None) => false,
+            (Goal::TimedDistance { time: limit, .. }, Some(at)) => at <= limit,
+            _ => true,
+        }
+    }
+
+    pub fn progress(&self, distance: f64, time: f64) -> GoalProgress {
+        let status = if self.is_met() {
+            GoalStatus::Reached
+        } else if self.completed_at.is_some() || matches!(self.goal, Goal::TimedDistance { time: limit, .. } if time > limit) {
+            GoalStatus::Missed
+        } else {
+            GoalStatus::InProgress
+        };
+        let (done, target, required_pace) = match self.goal {
+            Goal::Distance(target) => (distance, target, None),
+            Goal::Duration(target) => (time, target, None),
+            Goal::TimedDistance { distance: target, time: limit } => {
+                let remaining = (target - distance) / 1000.0;
+                let pace = (status == GoalStatus::InProgress && remaining > 0.0).then(|| (limit - time) / remaining);
+                (distance, target, pace)
+            }
+        };
+        GoalProgress {
+            goal: self.goal,
+            status,
+            done,
+            target,
+            required_pace,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{LocationSource, SessionEvent, TrackingConfig, TrackingSession};
+    use crate::tracking::sources::synthetic::SyntheticSource;
+
+    /// Runs `count` fixes at `speed` m/s through a session with `goal`
+    fn run(goal: Goal, speed: f64, count: usize) -> TrackingSession {
+        let config = TrackingConfig { warm_up: 0.0, smoothing: false, goal: Some(goal), ..TrackingConfig::default() };
+        let mut session = TrackingSession::with_config(config);
+        let mut source = SyntheticSource::new((55.75, 37.61), speed, 0.0, count);
+        while let Some(event) = source.next_event() {
+            let _ = session.handle_event(event);
+        }
+        session
+    }
+
+    fn goal_events(session: &mut TrackingSession) -> usize {
+        session.take_events().iter().filter(|e| matches!(e, SessionEvent::GoalReached(_))).count()
+    }
+
+    #[test]
+    fn distance_and_duration() {
+        let mut session = run(Goal::Distance(1000.0), 3.0, 400);
+        assert!(session.goal_met());
+        assert_eq!(goal_events(&mut session), 1);
+
+        let mut session = run(Goal::Duration(600.0), 3.0, 400);
+        assert!(!session.goal_met());
+        assert_eq!(goal_events(&mut session), 0);
+        let progress = session.goal_progress().unwrap();
+        assert_eq!(progress.status, GoalStatus::InProgress);
+        assert!((progress.fraction() - 399.0 / 600.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn timed_distance() {
+        // 1 km in 5 minutes needs 3.33 m/s
+        let goal = Goal::TimedDistance { distance: 1000.0, time: 300.0 };
+        let mut session = run(goal, 3.5, 400);
+        assert!(session.goal_met());
+        assert_eq!(goal_events(&mut session), 1);
+
+        let mut session = run(goal, 3.0, 400);
+        assert!(!session.goal_met());
+        assert_eq!(goal_events(&mut session), 0);
+        assert_eq!(session.goal_progress().unwrap().status, GoalStatus::Missed);
+
+        // 450 m at 3 m/s, 550 m to go in 150 s
+        let session = run(goal, 3.0, 151);
+        let progress = session.goal_progress().unwrap();
+        assert_eq!(progress.status, GoalStatus::InProgress);
+        assert!((progress.required_pace.unwrap() - 150.0 / 0.55).abs() < 0.1);
+        assert_eq!(progress.to_string(), "0.45 / 1.00 km, need 4:33/km");
+    }
+
+    #[test]
+    fn presets_cycle() {
+        let mut goal = None;
+        for _ in 0..Goal::PRESETS.len() {
+            goal = Goal::next_preset(goal);
+        }
+        assert_eq!(goal, None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use crate::tracking::{LocationEvent, TrackingConfig, TrackingSession};
+
+pub const JOURNAL_FILE_NAME: &str = "session_journal.jsonl";
+
+/// Entries written since the last fsync before the next one is forced
+const SYNC_BATCH: usize = 10;
+/// Longest time an entry may stay unsynced
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// One line of the journal.
+///
+/// Every location event of the recording is journaled, not only the accepted fixes, so replaying
+/// the journal through a new session makes exactly the same decisions as the original one.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum JournalEntry {
+    Start { config: Box<TrackingConfig> },
+    Event(LocationEvent),
+    Pause,
+    Resume,
+    Lap,
+    /// recording was recovered by a new process, later events use its clock
+    Recovered,
+}
+
+/// Append-only log of the recording in progress, synced to disk in batches
+pub struct Journal {
+    writer: BufWriter<File>,
+    unsynced: usize,
+    last_sync: Instant,
+}
+
+fn journal_path(dir: &str) -> String {
+    format!("{}/{}", dir, JOURNAL_FILE_NAME)
+}
+
+impl Journal {
+    /// Starts a new journal in `dir`, replacing any previous one
+    pub fn create(dir: &str, config: &TrackingConfig) -> Option<Self> {
+        Self::create_with(dir, &[JournalEntry::Start { config: Box::new(config.clone()) }])
+    }
+
+    /// Continues a recovered journal. The file is rewritten from `entries`, so new entries
+    /// don't end up behind a line broken by the crash.
+    pub fn restore(dir: &str, entries: &[JournalEntry]) -> Option<Self> {
+        Self::create_with(dir, entries)
+    }
+
+    fn create_with(dir: &str, entries: &[JournalEntry]) -> Option<Self> {
+        let path = journal_path(dir);
+        info!("Creating session journal {}", path);
+        let Ok(file) = File::create(&path) else {
+            warn!("Failed to create {}!", path);
+            return None;
+        };
+        let mut journal = Self::with_file(file);
+        for entry in entries {
+            journal.append(entry);
+        }
+        journal.sync();
+        Some(journal)
+    }
+
+    fn with_file(file: File) -> Self {
+        Journal {
+            writer: BufWriter::new(file),
+            unsynced: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) {
+        let line = serde_json::to_string(entry).unwrap();
+        if let Err(e) = writeln!(self.writer, "{}", line) {
+            warn!("Writing to journal failed! {:?}", e);
+            return;
+        }
+        self.unsynced += 1;
+        let important = !matches!(entry, JournalEntry::Event(_));
+        if important || self.unsynced >= SYNC_BATCH || self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.sync();
+        }
+    }
+
+    /// Flushes buffered entries and waits until they are on disk
+    pub fn sync(&mut self) {
+        if let Err(e) = self.writer.flush().and_then(|_| self.writer.get_ref().sync_data()) {
+            warn!("Syncing journal failed! {:?}", e);
+        }
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+    }
+
+    /// Deletes the journal in `dir`, done once its session is saved as a record
+    pub fn discard(dir: &str) {
+        let path = journal_path(dir);
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Failed to remove journal {}! {:?}", path, e);
+        }
+    }
+
+    /// Entries of the journal left in `dir` by a session that was never saved, `None` if there is none.
+    /// A line cut off by a crash ends the journal.
+    pub fn find_unfinished(dir: &str) -> Option<Vec<JournalEntry>> {
+        let path = journal_path(dir);
+        let file = File::open(&path).ok()?;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    warn!("Journal {} ends with a broken entry, ignoring the rest: {:?}", path, e);
+                    break;
+                }
+            }
+        }
+        if entries.is_empty() {
+            return None;
+        }
+        info!("Found unfinished session journal with {} entries", entries.len());
+        Some(entries)
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        self.sync();
+    }
+}
+
+/// Rebuilds the session recorded in a journal, `None` if the journal has no start entry
+pub fn replay(entries: &[JournalEntry]) -> Option<TrackingSession> {
+    let mut session: Option<TrackingSession> = None;
+    for entry in entries {
+        match entry {
+            JournalEntry::Start { config } => session = Some(TrackingSession::with_config((**config).clone())),
+            JournalEntry::Event(event) => {
+                if let Some(session) = &mut session {
+                    let _ = session.handle_event(event.clone());
+                }
+            }
+            JournalEntry::Pause => session.iter_mut().for_each(|s| s.pause()),
+            JournalEntry::Resume => session.iter_mut().for_each(|s| s.resume()),
+            JournalEntry::Lap => session.iter_mut().for_each(|s| s.mark_lap()),
+            JournalEntry::Recovered => session.iter_mut().for_each(|s| s.restart_clock()),
+        }
+    }
+    session
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use super::*;
+    use crate::tracking::LocationSource;
+    use crate::tracking::sources::synthetic::SyntheticSource;
+    use crate::tracking::test_dir::TestDir;
+
+    #[test]
+    fn replay_matches_recorded_session() {
+        let test_dir = TestDir::new("journal-replay");
+        let dir = test_dir.path();
+        let config = TrackingConfig::default();
+        let mut session = TrackingSession::with_config(config.clone());
+        let mut journal = Journal::create(dir, &config).unwrap();
+
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.5, 45.0, 120).jitter(2.0, 11);
+        let mut i = 0;
+        while let Some(event) = source.next_event() {
+            journal.append(&JournalEntry::Event(event.clone()));
+            let _ = session.handle_event(event);
+            match i {
+                40 => {
+                    journal.append(&JournalEntry::Lap);
+                    session.mark_lap();
+                }
+                60 => {
+                    journal.append(&JournalEntry::Pause);
+                    session.pause();
+                }
+                80 => {
+                    journal.append(&JournalEntry::Resume);
+                    session.resume();
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        // killed without finishing, the journal is never dropped cleanly
+        journal.sync();
+        std::mem::forget(journal);
+
+        let entries = Journal::find_unfinished(dir).unwrap();
+        let restored = replay(&entries).unwrap();
+        // JSON doesn't round-trip the last bits of a float
+        assert!((restored.total_distance() - session.total_distance()).abs() < 1e-6);
+        assert!((restored.total_time() - session.total_time()).abs() < 1e-6);
+        assert_eq!(restored.laps().len(), session.laps().len());
+        assert_eq!(restored.segments().len(), 2);
+        Journal::discard(dir);
+    }
+
+    #[test]
+    fn truncated_journal_is_recovered() {
+        let test_dir = TestDir::new("journal-truncated");
+        let dir = test_dir.path();
+        let mut journal = Journal::create(dir, &TrackingConfig::default()).unwrap();
+        journal.append(&JournalEntry::Pause);
+        drop(journal);
+
+        // a crash in the middle of a write leaves half a line
+        let mut file = OpenOptions::new().append(true).open(journal_path(dir)).unwrap();
+        write!(file, "{{\"Event\":{{\"Fix\":").unwrap();
+        let entries = Journal::find_unfinished(dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(replay(&entries).unwrap().is_paused());
+
+        Journal::discard(dir);
+        assert!(Journal::find_unfinished(dir).is_none());
+    }
+}
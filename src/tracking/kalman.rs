@@ -0,0 +1,131 @@
+//! Constant-velocity Kalman filter for smoothing location fixes.
+//!
+//! The filter works in local (north, east) meters. Both axes share the same motion model and the
+//! measurement noise is isotropic, so they are filtered independently.
+
+/// Variance of the initial velocity estimate, (m/s)^2
+const INITIAL_VELOCITY_VARIANCE: f64 = 25.0;
+
+/// Default acceleration noise, (m/s^2)^2. Covers walking and running direction changes.
+pub const DEFAULT_PROCESS_NOISE: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug)]
+struct Axis {
+    pos: f64,
+    vel: f64,
+    /// covariance of (pos, vel)
+    p: [[f64; 2]; 2],
+}
+
+impl Axis {
+    fn new(pos: f64, variance: f64) -> Self {
+        Axis {
+            pos,
+            vel: 0.0,
+            p: [[variance, 0.0], [0.0, INITIAL_VELOCITY_VARIANCE]],
+        }
+    }
+
+    fn predict(&mut self, dt: f64, q: f64) {
+        self.pos += self.vel * dt;
+
+        let [[p00, p01], [p10, p11]] = self.p;
+        let dt2 = dt * dt;
+        self.p = [
+            [p00 + dt * (p10 + p01) + dt2 * p11 + q * dt2 * dt2 / 4.0, p01 + dt * p11 + q * dt2 * dt / 2.0],
+            [p10 + dt * p11 + q * dt2 * dt / 2.0, p11 + q * dt2],
+        ];
+    }
+
+    fn correct(&mut self, z: f64, r: f64) {
+        let [[p00, p01], [p10, p11]] = self.p;
+        let s = p00 + r;
+        let k0 = p00 / s;
+        let k1 = p10 / s;
+
+        let y = z - self.pos;
+        self.pos += k0 * y;
+        self.vel += k1 * y;
+
+        self.p = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KalmanFilter {
+    process_noise: f64,
+    state: Option<(Axis, Axis)>,
+    last_timestamp: f64,
+}
+
+impl Default for KalmanFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROCESS_NOISE)
+    }
+}
+
+impl KalmanFilter {
+    pub fn new(process_noise: f64) -> Self {
+        KalmanFilter {
+            process_noise,
+            state: None,
+            last_timestamp: 0.0,
+        }
+    }
+
+    /// Feeds a measured position with its reported accuracy (1 sigma, meters).
+    /// Returns the filtered (north, east) position.
+    pub fn update(&mut self, north: f64, east: f64, accuracy: f64, timestamp: f64) -> (f64, f64) {
+        let r = accuracy.max(0.1).powi(2);
+
+        match &mut self.state {
+            Some((n, e)) => {
+                let dt = (timestamp - self.last_timestamp).max(0.0);
+                n.predict(dt, self.process_noise);
+                e.predict(dt, self.process_noise);
+                n.correct(north, r);
+                e.correct(east, r);
+            }
+            None => {
+                self.state = Some((Axis::new(north, r), Axis::new(east, r)));
+            }
+        }
+        self.last_timestamp = timestamp;
+
+        self.position().unwrap()
+    }
+
+    pub fn position(&self) -> Option<(f64, f64)> {
+        self.state.map(|(n, e)| (n.pos, e.pos))
+    }
+
+    /// Estimated (north, east) velocity in m/s
+    pub fn velocity(&self) -> Option<(f64, f64)> {
+        self.state.map(|(n, e)| (n.vel, e.vel))
+    }
+
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_velocity_converges() {
+        let mut filter = KalmanFilter::default();
+        for i in 0..60 {
+            let t = i as f64;
+            filter.update(3.0 * t, -1.5 * t, 5.0, t);
+        }
+        let (north, east) = filter.position().unwrap();
+        assert!((north - 177.0).abs() < 0.5 && (east + 88.5).abs() < 0.5, "{} {}", north, east);
+        let (v_north, v_east) = filter.velocity().unwrap();
+        assert!((v_north - 3.0).abs() < 0.05 && (v_east + 1.5).abs() < 0.05, "{} {}", v_north, v_east);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::tracking::SessionEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LapTrigger {
+    /// automatic split every N meters
+    Split,
+    /// lap button on the active training screen
+    Manual,
+    /// passed the start point again
+    StartPoint,
+}
+
+/// Finished lap or split
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Lap {
+    pub index: usize,
+    pub trigger: LapTrigger,
+    /// meters
+    pub distance: f64,
+    /// seconds
+    pub duration: f64,
+    /// seconds per kilometer, 0 if no distance was covered
+    pub pace: f64,
+}
+
+impl Lap {
+    fn new(index: usize, trigger: LapTrigger, distance: f64, duration: f64) -> Self {
+        Lap {
+            index,
+            trigger,
+            distance,
+            duration,
+            pace: pace(distance, duration),
+        }
+    }
+}
+
+/// Seconds per kilometer, 0 if no distance was covered
+pub fn pace(distance: f64, duration: f64) -> f64 {
+    if distance <= 0.0 {
+        return 0.0;
+    }
+    duration / distance * 1000.0
+}
+
+/// Formats seconds per kilometer as `m:ss`
+pub fn format_pace(pace: f64) -> String {
+    if pace <= 0.0 || !pace.is_finite() {
+        return "-:--".to_string();
+    }
+    let secs = pace.round() as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Formats seconds as `mm:ss`, or `h:mm:ss` from one hour on
+pub fn format_duration(duration: f64) -> String {
+    let secs = duration.max(0.0) as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+    else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LapConfig {
+    /// automatic split length in meters, 0 disables splits
+    pub split_distance: f64,
+    /// start a new lap every time the start point is passed
+    pub start_point_laps: bool,
+    /// passing within this distance of the start point counts, meters
+    pub start_point_radius: f64,
+    /// how far from the start point the user must get before the next pass counts, meters
+    pub start_point_rearm: f64,
+}
+
+impl Default for LapConfig {
+    fn default() -> Self {
+        LapConfig {
+            split_distance: 1000.0,
+            start_point_laps: false,
+            start_point_radius: 15.0,
+            start_point_rearm: 50.0,
+        }
+    }
+}
+
+/// Cuts the session into splits and laps from the accumulated distance and time
+#[derive(Clone, Debug, Default)]
+pub struct LapTracker {
+    splits: Vec<Lap>,
+    laps: Vec<Lap>,
+
+    split_start: (f64, f64),
+    lap_start: (f64, f64),
+    /// (distance, time) at the previous update, used to interpolate split boundaries
+    prev: (f64, f64),
+    /// start point lap is armed once the user got far enough from the start
+    start_point_armed: bool,
+}
+
+impl LapTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn splits(&self) -> &[Lap] {
+        &self.splits
+    }
+
+    pub fn laps(&self) -> &[Lap] {
+        &self.laps
+    }
+
+    /// (distance, duration) of the lap in progress
+    pub fn current_lap(&self, distance: f64, time: f64) -> (f64, f64) {
+        (distance - self.lap_start.0, time - self.lap_start.1)
+    }
+
+    /// Called after every accepted fix with the session totals and the (north, east) offset from the start point
+    pub fn update(&mut self, distance: f64, time: f64, position: (f64, f64), config: &LapConfig, events: &mut Vec<SessionEvent>) {
+        if config.split_distance > 0.0 {
+            let mut boundary = self.split_start.0 + config.split_distance;
+            while distance >= boundary {
+                let (prev_dist, prev_time) = self.prev;
+                let step = distance - prev_dist;
+                let boundary_time = if step > 0.0 {
+                    prev_time + (time - prev_time) * (boundary - prev_dist).max(0.0) / step
+                } else {
+                    time
+                };
+                let split = Lap::new(self.splits.len(), LapTrigger::Split, config.split_distance, boundary_time - self.split_start.1);
+                events.push(SessionEvent::Split(split.clone()));
+                self.splits.push(split);
+                self.split_start = (boundary, boundary_time);
+                boundary += config.split_distance;
+            }
+        }
+
+        if config.start_point_laps {
+            let from_start = (position.0 * position.0 + position.1 * position.1).sqrt();
+            if from_start > config.start_point_rearm {
+                self.start_point_armed = true;
+            }
+            else if self.start_point_armed && from_start < config.start_point_radius {
+                self.start_point_armed = false;
+                self.finish_lap(LapTrigger::StartPoint, distance, time, events);
+            }
+        }
+
+        self.prev = (distance, time);
+    }
+
+    /// Closes the lap in progress and starts a new one
+    pub fn finish_lap(&mut self, trigger: LapTrigger, distance: f64, time: f64, events: &mut Vec<SessionEvent>) {
+        let (lap_distance, lap_duration) = self.current_lap(distance, time);
+        let lap = Lap::new(self.laps.len(), trigger, lap_distance, lap_duration);
+        events.push(SessionEvent::Lap(lap.clone()));
+        self.laps.push(lap);
+        self.lap_start = (distance, time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_are_interpolated() {
+        let mut tracker = LapTracker::new();
+        let mut events = Vec::new();
+        // 300 m every 100 s
+        for i in 1..=7 {
+            tracker.update(i as f64 * 300.0, i as f64 * 100.0, (0.0, 0.0), &LapConfig::default(), &mut events);
+        }
+        let splits = tracker.splits();
+        assert_eq!(splits.len(), 2);
+        assert_eq!(events.len(), 2);
+        assert!((splits[0].duration - 1000.0 / 3.0).abs() < 1e-9);
+        assert!((splits[1].duration - 1000.0 / 3.0).abs() < 1e-9);
+        assert_eq!((splits[1].index, splits[1].trigger, splits[1].distance), (1, LapTrigger::Split, 1000.0));
+        assert_eq!(format_pace(splits[0].pace), "5:33");
+        assert!(tracker.laps().is_empty());
+    }
+
+    #[test]
+    fn manual_and_start_point_laps() {
+        let config = LapConfig { split_distance: 0.0, start_point_laps: true, ..LapConfig::default() };
+        let mut tracker = LapTracker::new();
+        let mut events = Vec::new();
+        tracker.update(100.0, 40.0, (30.0, 0.0), &config, &mut events);
+        tracker.finish_lap(LapTrigger::Manual, 120.0, 50.0, &mut events);
+        assert_eq!(tracker.current_lap(150.0, 60.0), (30.0, 10.0));
+
+        // close to the start before getting far enough away doesn't count
+        tracker.update(150.0, 60.0, (10.0, 0.0), &config, &mut events);
+        assert_eq!(tracker.laps().len(), 1);
+        tracker.update(250.0, 100.0, (60.0, 0.0), &config, &mut events);
+        tracker.update(320.0, 130.0, (10.0, 0.0), &config, &mut events);
+        tracker.update(330.0, 135.0, (5.0, 0.0), &config, &mut events);
+
+        let laps = tracker.laps();
+        assert_eq!(laps.len(), 2);
+        assert_eq!((laps[0].trigger, laps[0].distance, laps[0].duration), (LapTrigger::Manual, 120.0, 50.0));
+        assert_eq!((laps[1].trigger, laps[1].distance, laps[1].duration), (LapTrigger::StartPoint, 200.0, 80.0));
+        assert_eq!(events.len(), 2);
+        assert!(tracker.splits().is_empty());
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_duration(65.9), "01:05");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+        assert_eq!(format_pace(359.6), "6:00");
+        assert_eq!(format_pace(0.0), "-:--");
+    }
+}
@@ -0,0 +1,168 @@
+//! Platform-agnostic GPS session tracking.
+//!
+//! Location fixes come from a [`LocationSource`] (Android JNI callbacks, a recorded log, a synthetic
+//! generator) and are fed into a [`TrackingSession`], which owns all of the recording state.
+//! Nothing in here depends on the renderer, so the session can be driven and tested on desktop.
+
+pub mod activity;
+pub mod auto_pause;
+pub mod clock;
+pub mod config;
+pub mod cues;
+pub mod elevation;
+pub mod energy;
+pub mod gaps;
+pub mod geodesy;
+pub mod ghost;
+pub mod goals;
+pub mod journal;
+pub mod kalman;
+pub mod laps;
+pub mod outlier;
+pub mod recorder;
+pub mod route;
+pub mod session;
+pub mod simplify;
+pub mod sources;
+pub mod speed;
+#[cfg(test)]
+mod test_dir;
+pub mod track;
+pub mod workout;
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+pub use activity::ActivityType;
+pub use clock::ClockMapping;
+pub use goals::Goal;
+pub use laps::Lap;
+pub use energy::{set_user_profile, Sex, UserProfile, USER_PROFILE};
+pub use config::{clear_session_picks, FixRejection, set_tracking_config, TRACKING_CONFIG, TrackingConfig};
+pub use recorder::{Recorder, RecordingState};
+pub use session::{SessionDiagnostics, TrackingSession};
+pub use workout::{StepResult, WorkoutPlan};
+
+/// Single location fix, as reported by the location provider
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LocationMetric {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// horizontal accuracy in meters
+    pub accuracy: f64,
+    /// monotonic time in seconds, for deltas
+    pub timestamp: f64,
+    /// UTC seconds since the UNIX epoch, for export and display
+    pub utc_time: Option<f64>,
+
+    /// meters above the WGS-84 ellipsoid
+    pub altitude: Option<f64>,
+    /// meters
+    pub vertical_accuracy: Option<f64>,
+    /// Doppler speed over ground, m/s
+    pub speed: Option<f64>,
+    /// m/s
+    pub speed_accuracy: Option<f64>,
+    /// degrees clockwise from north
+    pub bearing: Option<f64>,
+}
+
+impl LocationMetric {
+    pub fn new(latitude: f64, longitude: f64, accuracy: f64, timestamp: f64) -> Self {
+        LocationMetric {
+            latitude,
+            longitude,
+            accuracy,
+            timestamp,
+            utc_time: None,
+
+            altitude: None,
+            vertical_accuracy: None,
+            speed: None,
+            speed_accuracy: None,
+            bearing: None,
+        }
+    }
+
+    pub fn with_utc_time(mut self, utc_time: f64) -> Self {
+        self.utc_time = Some(utc_time);
+        self
+    }
+
+    pub fn with_altitude(mut self, altitude: f64, vertical_accuracy: Option<f64>) -> Self {
+        self.altitude = Some(altitude);
+        self.vertical_accuracy = vertical_accuracy;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f64, speed_accuracy: Option<f64>) -> Self {
+        self.speed = Some(speed);
+        self.speed_accuracy = speed_accuracy;
+        self
+    }
+
+    pub fn with_bearing(mut self, bearing: f64) -> Self {
+        self.bearing = Some(bearing);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LocationEvent {
+    Fix(LocationMetric),
+    /// provider became available at the given monotonic time
+    ProviderEnabled(f64),
+    /// provider was turned off at the given monotonic time
+    ProviderDisabled(f64),
+}
+
+impl LocationEvent {
+    /// Monotonic time of the event
+    pub fn timestamp(&self) -> f64 {
+        match self {
+            LocationEvent::Fix(metric) => metric.timestamp,
+            LocationEvent::ProviderEnabled(timestamp) | LocationEvent::ProviderDisabled(timestamp) => *timestamp,
+        }
+    }
+}
+
+/// Notifications produced by a [`TrackingSession`] for the UI
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    AutoPaused,
+    AutoResumed,
+    Split(Lap),
+    Lap(Lap),
+    GoalReached(Goal),
+    /// a workout step was completed, the next one starts
+    StepFinished(StepResult),
+    /// cross-track error stayed beyond the off-course distance, meters
+    OffCourse(f64),
+    BackOnCourse,
+}
+
+/// Anything that can produce location events for a [`TrackingSession`]
+pub trait LocationSource {
+    /// Returns the next pending event, or `None` if nothing is available right now
+    fn next_event(&mut self) -> Option<LocationEvent>;
+
+    /// Source will never produce events again (end of file, generator exhausted)
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+/// Feeds every pending event of `source` into `session`. Returns the number of events processed.
+pub fn drain_source(session: &mut TrackingSession, source: &mut dyn LocationSource) -> usize {
+    let mut count = 0;
+    while let Some(event) = source.next_event() {
+        let _ = session.handle_event(event);
+        count += 1;
+    }
+    count
+}
+
+lazy_static! {
+    /// Recording shared between the location callbacks and the UI
+    pub static ref RECORDER: Mutex<Recorder> = Mutex::new(Recorder::new());
+}
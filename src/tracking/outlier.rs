@@ -0,0 +1,196 @@
+use crate::tracking::config::FixRejection;
+
+/// After this many rejected fixes in a row the reference point is considered wrong and dropped
+const MAX_CONSECUTIVE_REJECTIONS: usize = 5;
+
+/// Plausibility limits for consecutive fixes. Reported accuracy of both fixes is subtracted
+/// from the jump before the limits are applied, so noisy but honest fixes pass.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct OutlierConfig {
+    pub enabled: bool,
+    /// fastest believable movement, m/s
+    pub max_speed: f64,
+    /// fastest believable speed-up between two fixes, m/s^2
+    pub max_acceleration: f64,
+    /// jumps longer than this are held until the next fix shows whether they were a spike, meters
+    pub spike_distance: f64,
+    /// held fix is a spike if the next fix comes back closer than this fraction of the jump
+    pub spike_return_ratio: f64,
+}
+
+impl Default for OutlierConfig {
+    fn default() -> Self {
+        OutlierConfig {
+            enabled: true,
+            max_speed: 12.0,
+            max_acceleration: 6.0,
+            spike_distance: 15.0,
+            spike_return_ratio: 0.5,
+        }
+    }
+}
+
+/// Fix in local (north, east) meters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlanarFix {
+    pub timestamp: f64,
+    pub north: f64,
+    pub east: f64,
+    pub accuracy: f64,
+}
+
+impl PlanarFix {
+    fn distance(&self, other: &PlanarFix) -> f64 {
+        ((self.north - other.north).powi(2) + (self.east - other.east).powi(2)).sqrt()
+    }
+
+    /// Distance that can not be explained by the reported accuracy of both fixes
+    fn excess_distance(&self, other: &PlanarFix) -> f64 {
+        (self.distance(other) - self.accuracy - other.accuracy).max(0.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutlierCheck {
+    Plausible,
+    /// long jump, accept or drop once the next fix arrives
+    SpikeCandidate,
+}
+
+/// Rejects fixes implying impossible movement relative to the previously accepted one
+#[derive(Clone, Debug, Default)]
+pub struct OutlierFilter {
+    last: Option<PlanarFix>,
+    /// speed between the last two accepted fixes, m/s
+    last_speed: Option<f64>,
+    consecutive_rejections: usize,
+}
+
+impl OutlierFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(&mut self, fix: &PlanarFix, config: &OutlierConfig) -> Result<OutlierCheck, FixRejection> {
+        let Some(last) = self.last else {
+            return Ok(OutlierCheck::Plausible);
+        };
+        if !config.enabled {
+            return Ok(OutlierCheck::Plausible);
+        }
+        let dt = fix.timestamp - last.timestamp;
+        if dt <= 0.0 {
+            return Ok(OutlierCheck::Plausible);
+        }
+
+        let excess = fix.excess_distance(&last);
+        let speed = excess / dt;
+        let rejection = if speed > config.max_speed {
+            Some(FixRejection::ImplausibleSpeed { speed, max: config.max_speed })
+        }
+        else {
+            self.last_speed
+                .map(|last_speed| (speed - last_speed) / dt)
+                .filter(|&acceleration| acceleration > config.max_acceleration)
+                .map(|acceleration| FixRejection::ImplausibleAcceleration { acceleration, max: config.max_acceleration })
+        };
+
+        if let Some(rejection) = rejection {
+            self.consecutive_rejections += 1;
+            if self.consecutive_rejections >= MAX_CONSECUTIVE_REJECTIONS {
+                // the previous fix was the bad one, start over from here
+                self.reset();
+            }
+            return Err(rejection);
+        }
+        self.consecutive_rejections = 0;
+
+        if excess > config.spike_distance {
+            Ok(OutlierCheck::SpikeCandidate)
+        }
+        else {
+            Ok(OutlierCheck::Plausible)
+        }
+    }
+
+    /// Held `candidate` was a multipath spike if `next` returned towards the last accepted fix
+    pub fn is_spike(&self, candidate: &PlanarFix, next: &PlanarFix, config: &OutlierConfig) -> bool {
+        let Some(last) = self.last else {
+            return false;
+        };
+        let jump = candidate.distance(&last);
+        next.distance(&last) < jump * config.spike_return_ratio && next.excess_distance(candidate) > config.spike_distance
+    }
+
+    pub fn accept(&mut self, fix: PlanarFix) {
+        if let Some(last) = self.last {
+            let dt = fix.timestamp - last.timestamp;
+            if dt > 0.0 {
+                self.last_speed = Some(fix.excess_distance(&last) / dt);
+            }
+        }
+        self.last = Some(fix);
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.last_speed = None;
+        self.consecutive_rejections = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(timestamp: f64, north: f64, east: f64) -> PlanarFix {
+        PlanarFix { timestamp, north, east, accuracy: 3.0 }
+    }
+
+    #[test]
+    fn implausible_movement_is_rejected() {
+        let config = OutlierConfig::default();
+        let mut filter = OutlierFilter::new();
+        assert_eq!(filter.check(&fix(0.0, 0.0, 0.0), &config), Ok(OutlierCheck::Plausible));
+        filter.accept(fix(0.0, 0.0, 0.0));
+        filter.accept(fix(1.0, 1.5, 0.0));
+
+        // 300 m in 10 s is 29.4 m/s once both accuracies are subtracted
+        let Err(FixRejection::ImplausibleSpeed { speed, .. }) = filter.check(&fix(11.0, 301.5, 0.0), &config) else {
+            panic!("speed not rejected");
+        };
+        assert!((speed - 29.4).abs() < 1e-9);
+        // from standing to 10 m/s within a second
+        assert!(matches!(filter.check(&fix(2.0, 17.5, 0.0), &config), Err(FixRejection::ImplausibleAcceleration { .. })));
+
+        let disabled = OutlierConfig { enabled: false, ..config };
+        assert_eq!(filter.check(&fix(11.0, 301.5, 0.0), &disabled), Ok(OutlierCheck::Plausible));
+    }
+
+    #[test]
+    fn spike_needs_a_return() {
+        let config = OutlierConfig::default();
+        let mut filter = OutlierFilter::new();
+        filter.accept(fix(0.0, 0.0, 0.0));
+
+        let candidate = fix(2.0, 0.0, 25.0);
+        assert_eq!(filter.check(&candidate, &config), Ok(OutlierCheck::SpikeCandidate));
+        assert!(filter.is_spike(&candidate, &fix(4.0, 3.0, 0.0), &config));
+        // keeps going the same way
+        assert!(!filter.is_spike(&candidate, &fix(4.0, 0.0, 40.0), &config));
+    }
+
+    #[test]
+    fn reference_is_dropped_after_rejections() {
+        let config = OutlierConfig::default();
+        let mut filter = OutlierFilter::new();
+        filter.accept(fix(0.0, 0.0, 0.0));
+        for i in 1..MAX_CONSECUTIVE_REJECTIONS {
+            assert!(filter.check(&fix(i as f64, 500.0, 0.0), &config).is_err());
+        }
+        assert!(filter.check(&fix(5.0, 500.0, 0.0), &config).is_err());
+        // the fifth rejection forgot the bad reference
+        assert_eq!(filter.check(&fix(6.0, 500.0, 0.0), &config), Ok(OutlierCheck::Plausible));
+    }
+}
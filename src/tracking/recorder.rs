@@ -0,0 +1,437 @@
+use log::{info, warn};
+use crate::tracking::{FixRejection, LocationEvent, LocationSource, TrackingConfig, TrackingSession};
+use crate::tracking::cues::{CueEngine, CueSink};
+use crate::tracking::journal::{self, Journal, JournalEntry};
+
+/// What the UI should show for the recording, used to rebuild the screen stack after the surface comes back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingState {
+    /// no training in progress
+    Idle,
+    Recording,
+    /// stopped by the user, waiting for continue or finish
+    Paused,
+}
+
+/// Owns the session of the training in progress.
+///
+/// Location events go straight from the source into the recorder, so recording goes on while
+/// the GL surface is gone and no screen exists. The UI only reads the session and issues commands.
+/// Everything that changes the session is also written to a [`Journal`], so a training survives
+/// the process being killed.
+pub struct Recorder {
+    session: TrackingSession,
+    active: bool,
+    /// UI is detached, fix count at that moment
+    suspended_at: Option<usize>,
+    /// where the journal lives, no journaling without it
+    journal_dir: Option<String>,
+    journal: Option<Journal>,
+    /// journal of a training the previous process didn't finish
+    unfinished: Option<Vec<JournalEntry>>,
+    cues: CueEngine,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder {
+            session: TrackingSession::new(),
+            active: false,
+            suspended_at: None,
+            journal_dir: None,
+            journal: None,
+            unfinished: None,
+            cues: CueEngine::new(),
+        }
+    }
+
+    /// Enables journaling into `dir` and looks there for a training left unfinished
+    pub fn set_journal_dir(&mut self, dir: &str) {
+        self.journal_dir = Some(dir.to_string());
+        if !self.active {
+            self.unfinished = Journal::find_unfinished(dir);
+        }
+    }
+
+    /// Adds an output for the coaching cues of every training
+    pub fn add_cue_sink(&mut self, sink: Box<dyn CueSink>) {
+        self.cues.add_sink(sink);
+    }
+
+    /// A training of a previous run can be recovered
+    pub fn has_unfinished(&self) -> bool {
+        self.unfinished.is_some()
+    }
+
+    /// Rebuilds the unfinished training from its journal and continues recording it.
+    /// Returns false if there is nothing to recover.
+    pub fn recover(&mut self) -> bool {
+        let Some(entries) = self.unfinished.take() else {
+            return false;
+        };
+        let Some(session) = journal::replay(&entries) else {
+            warn!("Journal has no start entry, dropping it");
+            self.discard_journal();
+            return false;
+        };
+        info!("Recovered training with {} fixes", session.raw_track().count());
+        self.session = session;
+        self.active = true;
+        self.cues.reset();
+        self.journal = self.journal_dir.as_deref().and_then(|dir| Journal::restore(dir, &entries));
+        // fixes of this process come with timestamps of a new monotonic clock
+        self.journal(JournalEntry::Recovered);
+        self.session.restart_clock();
+        true
+    }
+
+    /// Starts a new training, the previous session is discarded
+    pub fn start(&mut self, config: TrackingConfig) {
+        info!("Recording started");
+        if self.unfinished.take().is_some() {
+            warn!("Starting over an unfinished training, its journal is replaced");
+        }
+        self.journal = self.journal_dir.as_deref().and_then(|dir| Journal::create(dir, &config));
+        self.session = TrackingSession::with_config(config);
+        self.active = true;
+        self.cues.reset();
+    }
+
+    /// Ends the training and hands over its session for saving.
+    /// The journal stays on disk until [`Recorder::discard_journal`] is called after the save.
+    pub fn finish(&mut self) -> TrackingSession {
+        info!("Recording finished");
+        self.active = false;
+        self.journal = None;
+        let mut session = std::mem::take(&mut self.session);
+        session.pause();
+        session
+    }
+
+    /// Deletes the journal of the last training, call once it is saved as a record
+    pub fn discard_journal(&mut self) {
+        self.journal = None;
+        self.unfinished = None;
+        if let Some(dir) = &self.journal_dir {
+            Journal::discard(dir);
+        }
+    }
+
+    fn journal(&mut self, entry: JournalEntry) {
+        if let Some(journal) = &mut self.journal {
+            journal.append(&entry);
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.active && !self.session.is_paused() {
+            self.journal(JournalEntry::Pause);
+            self.session.pause();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.active && self.session.is_paused() {
+            self.journal(JournalEntry::Resume);
+            self.session.resume();
+        }
+    }
+
+    pub fn mark_lap(&mut self) {
+        if self.active && self.session.has_initial_metric() && !self.session.is_paused() {
+            self.journal(JournalEntry::Lap);
+            let seen = self.session.pending_events().len();
+            self.session.mark_lap();
+            self.cues.update(&self.session, seen);
+        }
+    }
+
+    pub fn state(&self) -> RecordingState {
+        match (self.active, self.session.is_paused()) {
+            (false, _) => RecordingState::Idle,
+            (true, false) => RecordingState::Recording,
+            (true, true) => RecordingState::Paused,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn session(&self) -> &TrackingSession {
+        &self.session
+    }
+
+    pub fn session_mut(&mut self) -> &mut TrackingSession {
+        &mut self.session
+    }
+
+    /// Events outside of a training are dropped
+    pub fn handle_event(&mut self, event: LocationEvent) -> Result<(), FixRejection> {
+        if !self.active {
+            return Err(FixRejection::Paused);
+        }
+        self.journal(JournalEntry::Event(event.clone()));
+        let seen = self.session.pending_events().len();
+        let result = self.session.handle_event(event);
+        self.cues.update(&self.session, seen);
+        result
+    }
+
+    /// Time based cues, like a lost signal, for the stretches without location events
+    pub fn check_cues(&mut self) {
+        if self.active {
+            let rules = self.session.config().cues.clone();
+            self.cues.check(&self.session, &rules);
+        }
+    }
+
+    /// Feeds every pending event of `source`, returns the number of events taken from it
+    pub fn drain(&mut self, source: &mut dyn LocationSource) -> usize {
+        let mut count = 0;
+        while let Some(event) = source.next_event() {
+            let _ = self.handle_event(event);
+            count += 1;
+        }
+        count
+    }
+
+    /// UI surface is gone. Recording goes on.
+    pub fn suspend_ui(&mut self) {
+        info!("UI detached, recording state {:?}", self.state());
+        self.suspended_at = Some(self.session.raw_track().count());
+    }
+
+    /// UI surface is back, returns the state to rebuild the screens for
+    pub fn resume_ui(&mut self) -> RecordingState {
+        if let Some(fixes) = self.suspended_at.take() {
+            info!("UI reattached, {} fixes recorded while detached", self.session.raw_track().count().saturating_sub(fixes));
+        }
+        self.state()
+    }
+
+    pub fn is_ui_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{ActivityType, UserProfile};
+    use crate::tracking::sources::synthetic::SyntheticSource;
+    use crate::tracking::test_dir::TestDir;
+
+    fn config() -> TrackingConfig {
+        TrackingConfig {
+            smoothing: false,
+            ..TrackingConfig::default()
+        }
+    }
+
+    fn feed(recorder: &mut Recorder, source: &mut SyntheticSource, count: usize) {
+        for _ in 0..count {
+            let event = source.next_event().unwrap();
+            let _ = recorder.handle_event(event);
+        }
+    }
+
+    #[test]
+    fn fixes_accumulate_while_suspended() {
+        let mut recorder = Recorder::new();
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 90.0, 200);
+        recorder.start(config());
+
+        feed(&mut recorder, &mut source, 50);
+        let before = recorder.session().total_distance();
+        recorder.suspend_ui();
+        feed(&mut recorder, &mut source, 50);
+        assert!(recorder.is_ui_suspended());
+        assert_eq!(recorder.resume_ui(), RecordingState::Recording);
+
+        // warm-up drops the first 10 fixes
+        assert_eq!(recorder.session().raw_track().count(), 90);
+        assert!(recorder.session().total_distance() > before + 99.0);
+        assert_eq!(recorder.session().segments().len(), 1);
+    }
+
+    #[test]
+    fn repeated_suspend_resume_cycles() {
+        let mut recorder = Recorder::new();
+        let mut source = SyntheticSource::new((55.75, 37.61), 3.0, 0.0, 500);
+        recorder.start(config());
+
+        for _ in 0..10 {
+            feed(&mut recorder, &mut source, 20);
+            recorder.suspend_ui();
+            feed(&mut recorder, &mut source, 20);
+            assert_eq!(recorder.resume_ui(), RecordingState::Recording);
+        }
+        assert_eq!(recorder.session().raw_track().count(), 390);
+        assert!((recorder.session().total_distance() - 389.0 * 3.0).abs() < 1.0);
+        assert!(!recorder.is_ui_suspended());
+    }
+
+    #[test]
+    fn paused_state_survives_suspend() {
+        let mut recorder = Recorder::new();
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 200);
+        recorder.start(config());
+
+        feed(&mut recorder, &mut source, 40);
+        recorder.pause();
+        recorder.suspend_ui();
+        feed(&mut recorder, &mut source, 30);
+        assert_eq!(recorder.resume_ui(), RecordingState::Paused);
+        assert_eq!(recorder.session().raw_track().count(), 30);
+
+        recorder.resume();
+        feed(&mut recorder, &mut source, 30);
+        assert_eq!(recorder.state(), RecordingState::Recording);
+        assert_eq!(recorder.session().segments().len(), 2);
+    }
+
+    #[test]
+    fn events_are_dropped_when_idle() {
+        let mut recorder = Recorder::new();
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 100);
+
+        recorder.suspend_ui();
+        assert_eq!(recorder.drain(&mut source), 100);
+        assert_eq!(recorder.resume_ui(), RecordingState::Idle);
+        assert!(!recorder.session().has_initial_metric());
+    }
+
+    #[test]
+    fn finish_hands_over_session() {
+        let mut recorder = Recorder::new();
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 100);
+        recorder.start(config());
+        recorder.suspend_ui();
+        feed(&mut recorder, &mut source, 60);
+
+        let session = recorder.finish();
+        assert!(session.is_paused());
+        assert_eq!(session.raw_track().count(), 50);
+        assert_eq!(recorder.resume_ui(), RecordingState::Idle);
+        assert_eq!(recorder.session().raw_track().count(), 0);
+    }
+
+    #[test]
+    fn unfinished_training_is_recovered() {
+        let test_dir = TestDir::new("recorder-recover");
+        let dir = test_dir.path();
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 30.0, 200);
+
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        recorder.start(config());
+        feed(&mut recorder, &mut source, 60);
+        recorder.mark_lap();
+        recorder.pause();
+        feed(&mut recorder, &mut source, 10);
+        recorder.resume();
+        feed(&mut recorder, &mut source, 40);
+        let distance = recorder.session().total_distance();
+        // process killed, nothing is finished
+        drop(recorder);
+
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        assert!(recorder.has_unfinished());
+        assert_eq!(recorder.state(), RecordingState::Idle);
+        assert!(recorder.recover());
+        assert_eq!(recorder.state(), RecordingState::Recording);
+        assert!((recorder.session().total_distance() - distance).abs() < 1e-6);
+        assert_eq!(recorder.session().laps().len(), 1);
+
+        // recording continues into the same journal
+        feed(&mut recorder, &mut source, 20);
+        let distance = recorder.session().total_distance();
+        drop(recorder);
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        assert!(recorder.recover());
+        assert!((recorder.session().total_distance() - distance).abs() < 1e-6);
+
+        recorder.finish();
+        recorder.discard_journal();
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        assert!(!recorder.has_unfinished());
+    }
+
+    #[test]
+    fn recovery_after_reboot() {
+        let test_dir = TestDir::new("recorder-reboot");
+        let dir = test_dir.path();
+
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        recorder.start(config());
+        feed(&mut recorder, &mut SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 100).start_time(5000.0), 100);
+        let (distance, time) = (recorder.session().total_distance(), recorder.session().total_time());
+        drop(recorder);
+
+        // the device rebooted, its monotonic clock starts over
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        assert!(recorder.recover());
+        let mut source = SyntheticSource::new((55.7518, 37.61), 2.0, 0.0, 60).start_time(20.0);
+        feed(&mut recorder, &mut source, 60);
+
+        let session = recorder.session();
+        assert_eq!(session.segments().len(), 2);
+        assert!(session.gaps().is_empty());
+        // warm-up again, then 49 s in the new segment
+        assert!((session.total_time() - time - 49.0).abs() < 1e-6, "{} {}", session.total_time(), time);
+        assert!((session.total_distance() - distance - 98.0).abs() < 1.0);
+        assert!((session.elapsed_time_at(79.0) - 89.0 - 59.0).abs() < 1e-6, "{}", session.elapsed_time_at(79.0));
+
+        // replaying the journal gives the same session
+        drop(recorder);
+        let mut recorder = Recorder::new();
+        recorder.set_journal_dir(dir);
+        assert!(recorder.recover());
+        assert!((recorder.session().total_time() - time - 49.0).abs() < 1e-6);
+        recorder.finish();
+        recorder.discard_journal();
+    }
+
+    #[test]
+    fn backwards_fix_is_rejected() {
+        let mut recorder = Recorder::new();
+        recorder.start(config());
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 30).start_time(100.0);
+        feed(&mut recorder, &mut source, 30);
+        let time = recorder.session().total_time();
+
+        let late = LocationEvent::Fix(crate::tracking::LocationMetric::new(55.7505, 37.61, 3.0, 110.0));
+        assert_eq!(recorder.handle_event(late), Err(FixRejection::OutOfOrder(19.0)));
+        assert_eq!(recorder.session().total_time(), time);
+        assert_eq!(recorder.session().diagnostics().out_of_order, 1);
+    }
+
+    #[test]
+    fn calories_follow_classified_activity() {
+        let profile = UserProfile::default();
+        let mut calories = Vec::new();
+        for (activity, auto_classify) in [(ActivityType::Cycle, true), (ActivityType::Run, false), (ActivityType::Cycle, false)] {
+            let mut recorder = Recorder::new();
+            recorder.start(TrackingConfig { activity, auto_classify, ..config() });
+            let mut source = SyntheticSource::new((55.75, 37.61), 3.0, 90.0, 100);
+            feed(&mut recorder, &mut source, 100);
+            let session = recorder.finish();
+            calories.push(session.calories(&profile));
+        }
+        // a run picked as a ride is stored as a run and counted as one
+        assert_eq!(calories[0], calories[1]);
+        assert!(calories[1] > calories[2]);
+    }
+}
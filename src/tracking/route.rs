@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use crate::tracking::{LocationMetric, SessionEvent};
+use crate::tracking::geodesy::LocalProjection;
+use crate::tracking::sources::gpx;
+
+/// Matching looks this far ahead of the previous position along the route, meters
+const LOOK_AHEAD: f64 = 500.0;
+/// and this far back, so a loop or an out-and-back isn't matched to the wrong leg
+const LOOK_BEHIND: f64 = 50.0;
+/// Meters of cross-track error a meter of going backwards along the route is worth.
+/// Where two legs overlap, the one ahead wins.
+const BACKTRACK_PENALTY: f64 = 0.1;
+
+/// Folder of the data directory with the GPX files offered as planned routes
+pub const ROUTES_DIR: &str = "routes";
+
+/// GPX files of the routes folder in `dir`, sorted by name
+pub fn planned_routes(dir: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(Path::new(dir).join(ROUTES_DIR)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gpx")))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Planned route picked for the next session
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Route {
+    pub name: String,
+    /// (latitude, longitude) in order of travel
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Route {
+    /// `None` for fewer than two points
+    pub fn new(name: &str, points: Vec<(f64, f64)>) -> Option<Self> {
+        (points.len() >= 2).then(|| Route { name: name.to_string(), points })
+    }
+
+    /// Route points of a GPX file, named after the file
+    pub fn from_gpx_file(path: &Path) -> Option<Self> {
+        let Ok(file) = File::open(path) else {
+            warn!("Failed to open route {}", path.display());
+            return None;
+        };
+        let points = match gpx::parse_route(BufReader::new(file)) {
+            Ok(points) => points,
+            Err(e) => {
+                warn!("Bad route file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let name = path.file_stem().map_or("route".into(), |stem| stem.to_string_lossy());
+        Self::new(&name, points.iter().map(|m| (m.latitude, m.longitude)).collect())
+    }
+
+    /// Route along the stored track of a past record, segments are joined
+    pub fn from_track(name: &str, segments: &[Vec<LocationMetric>]) -> Option<Self> {
+        Self::new(name, segments.iter().flatten().map(|m| (m.latitude, m.longitude)).collect())
+    }
+}
+
+/// When the session counts as off the route
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct OffCourseConfig {
+    /// cross-track error, meters
+    pub distance: f64,
+    /// consecutive fixes beyond `distance` before the off-course event
+    pub fixes: usize,
+}
+
+impl Default for OffCourseConfig {
+    fn default() -> Self {
+        OffCourseConfig {
+            distance: 30.0,
+            fixes: 3,
+        }
+    }
+}
+
+/// Where the session stands on its route
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteProgress {
+    /// meters along the route to the closest point
+    pub along: f64,
+    pub remaining: f64,
+    /// distance from the route, meters
+    pub cross_track: f64,
+    pub off_course: bool,
+}
+
+/// Closest point of the route to a position
+#[derive(Clone, Copy, Debug)]
+struct Match {
+    segment: usize,
+    along: f64,
+    cross_track: f64,
+    point: (f64, f64),
+}
+
+/// Matches accepted fixes to a route
+#[derive(Clone, Debug)]
+pub struct RouteTracker {
+    route: Route,
+    projection: LocalProjection,
+    /// route points as (north, east) meters from the first one
+    points: Vec<(f64, f64)>,
+    /// distance along the route at every point
+    cumulative: Vec<f64>,
+    last: Option<Match>,
+    /// latest fix, projected
+    position: Option<(f64, f64)>,
+    /// consecutive fixes beyond the off-course distance
+    off_fixes: usize,
+    off_course: bool,
+}
+
+impl RouteTracker {
+    pub fn new(route: Route) -> Self {
+        let (lat, lon) = route.points[0];
+        let projection = LocalProjection::new(lat, lon);
+        let points: Vec<_> = route.points.iter().map(|&(lat, lon)| projection.project(lat, lon)).collect();
+        let mut cumulative = vec![0.0];
+        for pair in points.windows(2) {
+            let length = (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1);
+            cumulative.push(cumulative.last().unwrap() + length);
+        }
+        RouteTracker {
+            route,
+            projection,
+            points,
+            cumulative,
+            last: None,
+            position: None,
+            off_fixes: 0,
+            off_course: false,
+        }
+    }
+
+    pub fn route(&self) -> &Route {
+        &self.route
+    }
+
+    pub fn length(&self) -> f64 {
+        *self.cumulative.last().unwrap()
+    }
+
+    /// Route as (north, east) meters from its first point
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Part of the route covered so far, in the same units as [`Self::points`]
+    pub fn covered(&self) -> Vec<(f64, f64)> {
+        let Some(last) = self.last else {
+            return Vec::new();
+        };
+        let mut covered = self.points[..=last.segment].to_vec();
+        covered.push(last.point);
+        covered
+    }
+
+    /// Latest fix in the units of [`Self::points`]
+    pub fn position(&self) -> Option<(f64, f64)> {
+        self.position
+    }
+
+    /// Closest point on the segments between `first` and `last`, going back from `along` is penalized
+    fn closest(&self, position: (f64, f64), first: usize, last: usize, along: f64) -> Option<Match> {
+        let cost = |m: &Match| m.cross_track + (along - m.along).max(0.0) * BACKTRACK_PENALTY;
+        (first..last).map(|i| {
+            let (a, b) = (self.points[i], self.points[i + 1]);
+            let (dn, de) = (b.0 - a.0, b.1 - a.1);
+            let length2 = dn * dn + de * de;
+            let t = if length2 > 0.0 { (((position.0 - a.0) * dn + (position.1 - a.1) * de) / length2).clamp(0.0, 1.0) } else { 0.0 };
+            let point = (a.0 + dn * t, a.1 + de * t);
+            Match {
+                segment: i,
+                along: self.cumulative[i] + (self.cumulative[i + 1] - self.cumulative[i]) * t,
+                cross_track: (position.0 - point.0).hypot(position.1 - point.1),
+                point,
+            }
+        }).min_by(|a, b| cost(a).total_cmp(&cost(b)))
+    }
+
+    /// Takes an accepted fix, raises the off-course and back-on-course events
+    pub fn update(&mut self, latitude: f64, longitude: f64, config: &OffCourseConfig, events: &mut Vec<SessionEvent>) {
+        let position = self.projection.project(latitude, longitude);
+        self.position = Some(position);
+        let segments = self.points.len() - 1;
+        let along = self.last.map_or(0.0, |last| last.along);
+        let global = self.closest(position, 0, segments, along);
+        let found = match self.last {
+            None => global,
+            Some(last) => {
+                // segments around the previous match first, the whole route if the session left it
+                let first = self.cumulative.partition_point(|&d| d <= last.along - LOOK_BEHIND).saturating_sub(1);
+                let end = self.cumulative.partition_point(|&d| d < last.along + LOOK_AHEAD).min(segments);
+                let local = self.closest(position, first.min(last.segment), end.max(last.segment + 1), along);
+                match (local, global) {
+                    (Some(local), Some(global)) if local.cross_track > config.distance && global.cross_track <= config.distance => Some(global),
+                    (local, global) => local.or(global),
+                }
+            }
+        };
+        let Some(found) = found else {
+            return;
+        };
+        self.last = Some(found);
+
+        if found.cross_track > config.distance {
+            self.off_fixes += 1;
+            if !self.off_course && self.off_fixes >= config.fixes {
+                info!("Off course by {:.0}m at {:.0}m along {}", found.cross_track, found.along, self.route.name);
+                self.off_course = true;
+                events.push(SessionEvent::OffCourse(found.cross_track));
+            }
+        } else {
+            self.off_fixes = 0;
+            if self.off_course {
+                info!("Back on course at {:.0}m along {}", found.along, self.route.name);
+                self.off_course = false;
+                events.push(SessionEvent::BackOnCourse);
+            }
+        }
+    }
+
+    /// `None` before the first fix
+    pub fn progress(&self) -> Option<RouteProgress> {
+        let last = self.last?;
+        Some(RouteProgress {
+            along: last.along,
+            remaining: self.length() - last.along,
+            cross_track: last.cross_track,
+            off_course: self.off_course,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `north`, `east` meters from the route origin
+    fn position(tracker: &RouteTracker, north: f64, east: f64) -> (f64, f64) {
+        tracker.projection.unproject(north, east)
+    }
+
+    fn feed(tracker: &mut RouteTracker, points: &[(f64, f64)], events: &mut Vec<SessionEvent>) {
+        for &(north, east) in points {
+            let (lat, lon) = position(tracker, north, east);
+            tracker.update(lat, lon, &OffCourseConfig::default(), events);
+        }
+    }
+
+    #[test]
+    fn progress_and_off_course() {
+        // 1 km east, then 1 km north
+        let origin = LocalProjection::new(55.75, 37.61);
+        let corners = [(0.0, 0.0), (0.0, 1000.0), (1000.0, 1000.0)];
+        let route = Route::new("L", corners.iter().map(|&(n, e)| origin.unproject(n, e)).collect()).unwrap();
+        let mut tracker = RouteTracker::new(route);
+        let mut events = Vec::new();
+        assert!((tracker.length() - 2000.0).abs() < 1e-6);
+
+        feed(&mut tracker, &[(5.0, 100.0), (-8.0, 400.0), (10.0, 990.0), (300.0, 1005.0)], &mut events);
+        let progress = tracker.progress().unwrap();
+        assert!((progress.along - 1300.0).abs() < 0.5, "{:?}", progress);
+        assert!((progress.remaining - 700.0).abs() < 0.5);
+        assert!((progress.cross_track - 5.0).abs() < 0.5);
+        assert!(events.is_empty());
+
+        // a wrong turn east, two fixes out are not enough
+        feed(&mut tracker, &[(310.0, 1040.0), (310.0, 1080.0)], &mut events);
+        assert!(events.is_empty());
+        feed(&mut tracker, &[(310.0, 1120.0), (310.0, 1160.0)], &mut events);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], SessionEvent::OffCourse(error) if (error - 120.0).abs() < 0.5));
+        assert!(tracker.progress().unwrap().off_course);
+
+        feed(&mut tracker, &[(320.0, 1010.0)], &mut events);
+        assert_eq!(events.last(), Some(&SessionEvent::BackOnCourse));
+        assert_eq!(tracker.covered().len(), 3);
+    }
+
+    #[test]
+    fn out_and_back_keeps_the_leg() {
+        // 500 m north and back along the same line
+        let origin = LocalProjection::new(55.75, 37.61);
+        let route = Route::new("out and back", [(0.0, 0.0), (500.0, 0.0), (0.0, 0.0)].iter().map(|&(n, e)| origin.unproject(n, e)).collect()).unwrap();
+        let mut tracker = RouteTracker::new(route);
+        let mut events = Vec::new();
+
+        let out: Vec<_> = (0..=50).map(|i| (i as f64 * 10.0, 2.0)).collect();
+        let back: Vec<_> = (0..=40).map(|i| (500.0 - i as f64 * 10.0, -2.0)).collect();
+        feed(&mut tracker, &out, &mut events);
+        feed(&mut tracker, &back, &mut events);
+        // at 100 m north on the way back
+        assert!((tracker.progress().unwrap().along - 900.0).abs() < 0.5, "{:?}", tracker.progress());
+    }
+
+    #[test]
+    fn route_from_gpx() {
+        let gpx = r#"<gpx><rte><name>Park</name>
+            <rtept lat="55.75" lon="37.61"></rtept><rtept lat="55.76" lon="37.61"/>
+            </rte><trk><trkseg><trkpt lat="1" lon="1"/></trkseg></trk></gpx>"#;
+        let points = gpx::parse_route(gpx.as_bytes()).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[1].latitude, points[1].longitude), (55.76, 37.61));
+    }
+}
@@ -0,0 +1,1019 @@
+use std::time::Instant;
+use log::info;
+use crate::tracking::{ClockMapping, LocationEvent, LocationMetric, SessionEvent};
+use crate::tracking::activity::{self, ActivityType};
+use crate::tracking::auto_pause::{AutoPauseDetector, AutoPauseTransition};
+use crate::tracking::geodesy::{self, LocalProjection};
+use crate::tracking::config::{AccuracyGate, FixRejection, TrackingConfig};
+use crate::tracking::elevation::ElevationTracker;
+use crate::tracking::energy::{EffortAccumulator, UserProfile};
+use crate::tracking::gaps::{Gap, GapPolicy, GapReason, GapReasonTracker};
+use crate::tracking::ghost::GhostGap;
+use crate::tracking::goals::{Goal, GoalProgress, GoalTracker};
+use crate::tracking::route::{RouteProgress, RouteTracker};
+use crate::tracking::workout::{StepProgress, StepResult, WorkoutPlan, WorkoutTracker};
+use crate::tracking::kalman::KalmanFilter;
+use crate::tracking::laps::{self, Lap, LapTracker, LapTrigger};
+use crate::tracking::outlier::{OutlierCheck, OutlierFilter, PlanarFix};
+use crate::tracking::simplify::douglas_peucker;
+use crate::tracking::speed::SpeedEstimator;
+use crate::tracking::track::TrackSegment;
+
+/// Counts of what happened to incoming fixes, for troubleshooting bad recordings
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionDiagnostics {
+    pub accepted: usize,
+    pub warm_up: usize,
+    pub poor_accuracy: usize,
+    pub implausible_speed: usize,
+    pub implausible_acceleration: usize,
+    /// held jumps dropped after the next fix came back
+    pub spikes: usize,
+    pub out_of_order: usize,
+    /// held jumps recorded unchecked because the segment closed before the next fix
+    pub unconfirmed_jumps: usize,
+}
+
+impl SessionDiagnostics {
+    /// Fixes dropped for any reason other than a pause
+    pub fn rejected(&self) -> usize {
+        self.warm_up + self.poor_accuracy + self.implausible_speed + self.implausible_acceleration + self.spikes + self.out_of_order
+    }
+
+    fn count(&mut self, rejection: FixRejection) {
+        match rejection {
+            FixRejection::WarmUp(_) => self.warm_up += 1,
+            FixRejection::PoorAccuracy { .. } => self.poor_accuracy += 1,
+            FixRejection::ImplausibleSpeed { .. } => self.implausible_speed += 1,
+            FixRejection::ImplausibleAcceleration { .. } => self.implausible_acceleration += 1,
+            FixRejection::OutOfOrder(_) => self.out_of_order += 1,
+            FixRejection::Paused | FixRejection::AutoPaused | FixRejection::Held => {}
+        }
+    }
+}
+
+/// Recording state of a single training
+pub struct TrackingSession {
+    config: TrackingConfig,
+    accuracy_gate: AccuracyGate,
+    outliers: OutlierFilter,
+    /// long jump waiting for the next fix to tell whether it is a spike
+    held_fix: Option<(LocationMetric, PlanarFix)>,
+    last_rejection: Option<FixRejection>,
+    diagnostics: SessionDiagnostics,
+    auto_pause: AutoPauseDetector,
+    laps: LapTracker,
+    goal: Option<GoalTracker>,
+    workout: Option<WorkoutTracker>,
+    route: Option<RouteTracker>,
+    speed: SpeedEstimator,
+    /// current speed at every moving fix, for activity classification
+    speed_samples: Vec<f64>,
+    elevation: ElevationTracker,
+    effort: EffortAccumulator,
+    gaps: Vec<Gap>,
+    gap_reason: GapReasonTracker,
+    /// (event timestamp, when it was handled), maps wall clock to the fix timebase
+    clock: Option<(f64, Instant)>,
+    /// maps the fix timebase to UTC, fixed by the first fix that reports UTC
+    utc_clock: Option<ClockMapping>,
+    /// (elapsed time, event timestamp) the elapsed time is counted from, moved by [`Self::restart_clock`]
+    elapsed_anchor: Option<(f64, f64)>,
+    /// elapsed time before the timebase was restarted, anchored again by the next event
+    elapsed_before_restart: Option<f64>,
+    /// produced but not yet taken by the UI
+    events: Vec<SessionEvent>,
+
+    available_since: Option<f64>,
+    gps_acc_good: bool,
+    last_known_acc: Option<f64>,
+    initial_metric: Option<LocationMetric>,
+    projection: Option<LocalProjection>,
+    /// smoothing filter between incoming fixes and the accumulators, `None` if disabled
+    filter: Option<KalmanFilter>,
+    /// all recorded segments, the last one is being recorded unless `segment_open` is false
+    segments: Vec<TrackSegment>,
+    /// next accepted fix is appended to the last segment instead of opening a new one
+    segment_open: bool,
+    total_time: f64,
+    /// part of `total_time` spent above `moving_speed`
+    moving_time: f64,
+    total_distance: f64,
+    paused: bool,
+}
+
+impl Default for TrackingSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrackingSession {
+    pub fn new() -> Self {
+        Self::with_config(TrackingConfig::default())
+    }
+
+    pub fn with_config(config: TrackingConfig) -> Self {
+        TrackingSession {
+            filter: config.smoothing.then(KalmanFilter::default),
+            goal: config.goal.map(GoalTracker::new),
+            workout: config.workout.clone().map(WorkoutTracker::new),
+            route: config.route.clone().map(RouteTracker::new),
+            speed: SpeedEstimator::new(config.speed_window),
+            speed_samples: Vec::new(),
+            config,
+            accuracy_gate: AccuracyGate::new(),
+            outliers: OutlierFilter::new(),
+            held_fix: None,
+            last_rejection: None,
+            diagnostics: SessionDiagnostics::default(),
+            auto_pause: AutoPauseDetector::new(),
+            laps: LapTracker::new(),
+            elevation: ElevationTracker::new(),
+            effort: EffortAccumulator::new(),
+            gaps: Vec::new(),
+            gap_reason: GapReasonTracker::new(),
+            clock: None,
+            utc_clock: None,
+            elapsed_anchor: None,
+            elapsed_before_restart: None,
+            events: Vec::new(),
+
+            available_since: None,
+            initial_metric: None,
+            projection: None,
+            segments: Vec::new(),
+            segment_open: false,
+            gps_acc_good: false,
+            last_known_acc: None,
+            total_time: 0.0,
+            moving_time: 0.0,
+            total_distance: 0.0,
+            paused: false
+        }
+    }
+
+    /// Processes a single event from a location source. Returns the reason if a fix was not used.
+    pub fn handle_event(&mut self, event: LocationEvent) -> Result<(), FixRejection> {
+        if let Some(elapsed) = self.elapsed_before_restart.take() {
+            self.elapsed_anchor = Some((elapsed, event.timestamp()));
+        }
+        match event {
+            LocationEvent::Fix(metric) => {
+                self.clock = Some((metric.timestamp, Instant::now()));
+                if self.utc_clock.is_none() {
+                    self.utc_clock = ClockMapping::from_fix(&metric);
+                }
+                let res = self.update_location(metric);
+                if let Err(rejection) = res {
+                    info!("Fix rejected: {}", rejection);
+                    self.last_rejection = Some(rejection);
+                    self.diagnostics.count(rejection);
+                    self.gap_reason.rejected(rejection);
+                }
+                else {
+                    self.last_rejection = None;
+                }
+                res
+            }
+            LocationEvent::ProviderEnabled(timestamp) => {
+                self.clock = Some((timestamp, Instant::now()));
+                self.available_since = Some(timestamp);
+                Ok(())
+            }
+            LocationEvent::ProviderDisabled(timestamp) => {
+                self.clock = Some((timestamp, Instant::now()));
+                self.available_since = None;
+                self.gap_reason.provider_disabled();
+                Ok(())
+            }
+        }
+    }
+
+    fn update_location(&mut self, metric: LocationMetric) -> Result<(), FixRejection> {
+        if self.paused {
+            return Err(FixRejection::Paused);
+        }
+
+        if self.available_since.is_none() {
+            self.available_since = Some(metric.timestamp);
+        }
+
+        let last_fix = self.segments.last().filter(|_| self.segment_open).and_then(TrackSegment::end_time);
+        if let Some(last_fix) = last_fix.filter(|&last_fix| metric.timestamp < last_fix) {
+            return Err(FixRejection::OutOfOrder(last_fix - metric.timestamp));
+        }
+
+        self.last_known_acc = Some(metric.accuracy);
+        let accuracy_check = self.accuracy_gate.check(metric.accuracy, &self.config);
+        if let Some(available_since) = self.available_since {
+            let elapsed = metric.timestamp - available_since;
+            if elapsed < self.config.warm_up {
+                return Err(FixRejection::WarmUp(self.config.warm_up - elapsed));
+            }
+        }
+
+        self.gps_acc_good = accuracy_check.is_ok();
+        accuracy_check?;
+
+        let projection = *self.projection.get_or_insert_with(|| {
+            info!("Initial metric recorded! Training is started");
+            LocalProjection::new(metric.latitude, metric.longitude)
+        });
+        if self.initial_metric.is_none() {
+            self.elapsed_anchor = Some((0.0, metric.timestamp));
+            self.initial_metric = Some(LocationMetric {
+                utc_time: self.utc_clock.map(|clock| clock.utc(metric.timestamp)).or(metric.utc_time),
+                ..metric.clone()
+            });
+        }
+
+        let (north, east) = projection.project(metric.latitude, metric.longitude);
+        let fix = PlanarFix {
+            timestamp: metric.timestamp,
+            north,
+            east,
+            accuracy: metric.accuracy,
+        };
+        if let Some((held_metric, held_fix)) = self.held_fix.take() {
+            if self.outliers.is_spike(&held_fix, &fix, &self.config.outliers) {
+                info!("Dropping spike at {}", held_metric.timestamp);
+                self.diagnostics.spikes += 1;
+            }
+            else {
+                self.outliers.accept(held_fix);
+                let _ = self.record_fix(held_metric, projection);
+            }
+        }
+        match self.outliers.check(&fix, &self.config.outliers)? {
+            OutlierCheck::SpikeCandidate => {
+                self.held_fix = Some((metric, fix));
+                return Err(FixRejection::Held);
+            }
+            OutlierCheck::Plausible => self.outliers.accept(fix),
+        }
+
+        self.record_fix(metric, projection)
+    }
+
+    /// Adds a fix that passed all checks to the track
+    fn record_fix(&mut self, mut metric: LocationMetric, projection: LocalProjection) -> Result<(), FixRejection> {
+        if self.config.auto_pause.enabled {
+            let (north, east) = projection.project(metric.latitude, metric.longitude);
+            match self.auto_pause.update(metric.timestamp, north, east, &self.config.auto_pause) {
+                Some(AutoPauseTransition::Pause) => {
+                    info!("Movement stopped, auto-pausing");
+                    self.segment_open = false;
+                    self.speed.reset();
+                    self.events.push(SessionEvent::AutoPaused);
+                }
+                Some(AutoPauseTransition::Resume) => {
+                    info!("Movement detected, auto-resuming");
+                    self.events.push(SessionEvent::AutoResumed);
+                }
+                None => {}
+            }
+            if self.auto_pause.is_paused() {
+                return Err(FixRejection::AutoPaused);
+            }
+        }
+
+        let gap_policy = self.close_gap(&metric);
+        if gap_policy == Some(GapPolicy::Exclude) {
+            self.segment_open = false;
+        }
+
+        if !self.segment_open {
+            info!("Opening segment {}", self.segments.len());
+            self.speed.reset();
+            self.segments.push(TrackSegment::new());
+            self.segment_open = true;
+            if let Some(filter) = &mut self.filter {
+                filter.reset();
+            }
+        }
+        // one mapping for the whole session keeps UTC times consistent with the deltas
+        if let Some(clock) = self.utc_clock {
+            metric.utc_time = Some(clock.utc(metric.timestamp));
+        }
+        let segment = self.segments.last_mut().unwrap();
+        // previous point of the same segment, paused gaps are never bridged
+        let prev_metric = segment.points().last().cloned();
+
+        segment.raw.push(metric.clone());
+        let metric = match &mut self.filter {
+            Some(filter) => {
+                let (north, east) = projection.project(metric.latitude, metric.longitude);
+                let (north, east) = filter.update(north, east, metric.accuracy, metric.timestamp);
+                let (latitude, longitude) = projection.unproject(north, east);
+                let filtered = LocationMetric {
+                    latitude,
+                    longitude,
+                    ..metric
+                };
+                segment.filtered.push(filtered.clone());
+                filtered
+            }
+            None => metric,
+        };
+        let (lat_offset, lon_offset) = projection.project(metric.latitude, metric.longitude);
+
+        // (timestamp, total distance, total time, offset) of points filling an interpolated gap
+        let mut gap_fill = Vec::new();
+        let step_time = prev_metric.as_ref().map_or(0.0, |prev| metric.timestamp - prev.timestamp);
+        if let Some(prev_metric) = prev_metric {
+            let distance = geodesy::distance(prev_metric.latitude, prev_metric.longitude, metric.latitude, metric.longitude);
+            let time_diff = metric.timestamp - prev_metric.timestamp;
+
+            if gap_policy == Some(GapPolicy::Interpolate) {
+                let (prev_north, prev_east) = projection.project(prev_metric.latitude, prev_metric.longitude);
+                let steps = (time_diff / self.config.gaps.interpolation_step.max(0.1)).ceil() as usize;
+                for step in 1..steps {
+                    let f = step as f64 / steps as f64;
+                    let offset = (prev_north + (lat_offset - prev_north) * f, prev_east + (lon_offset - prev_east) * f);
+                    let timestamp = prev_metric.timestamp + time_diff * f;
+                    segment.display.push(LocationMetric::new(offset.0, offset.1, metric.accuracy, timestamp));
+                    gap_fill.push((timestamp, self.total_distance + distance * f, self.total_time + time_diff * f, offset));
+                }
+            }
+
+            segment.distance += distance;
+            self.total_distance += distance;
+            segment.time += time_diff;
+            self.total_time += time_diff;
+        }
+        info!("Offset: Lat: {}, Lon: {}", lat_offset, lon_offset);
+
+        if let Some(altitude) = metric.altitude {
+            self.elevation.update(altitude, metric.vertical_accuracy);
+        }
+
+        segment.display.push(LocationMetric {
+            latitude: lat_offset,
+            longitude: lon_offset,
+            ..metric.clone()
+        });
+
+        for (timestamp, distance, time, offset) in gap_fill {
+            self.speed.update(timestamp, distance, metric.accuracy, None);
+            self.laps.update(distance, time, offset, &self.config.laps, &mut self.events);
+        }
+        let doppler = metric.speed.zip(metric.speed_accuracy);
+        self.speed.update(metric.timestamp, self.total_distance, metric.accuracy, doppler);
+        if self.speed.speed() >= self.config.moving_speed {
+            self.moving_time += step_time;
+            self.speed_samples.push(self.speed.speed());
+        }
+        self.effort.update(self.speed.speed(), step_time);
+        self.laps.update(self.total_distance, self.total_time, (lat_offset, lon_offset), &self.config.laps, &mut self.events);
+        if let Some(goal) = &mut self.goal {
+            if goal.update(self.total_distance, self.total_time) {
+                info!("Goal {} reached", goal.goal());
+                self.events.push(SessionEvent::GoalReached(goal.goal()));
+            }
+        }
+        if let Some(workout) = &mut self.workout {
+            workout.update(self.total_distance, self.total_time, &mut self.events);
+        }
+        if let Some(route) = &mut self.route {
+            route.update(metric.latitude, metric.longitude, &self.config.off_course, &mut self.events);
+        }
+
+        self.diagnostics.accepted += 1;
+        self.gap_reason.reset();
+        info!("\nTotal time: {}, total distance: {}", self.total_time, self.total_distance);
+        info!("\nAvg speed: {}", self.avg_speed());
+        Ok(())
+    }
+
+    /// Records a gap if `metric` is the first fix after a signal loss, returns its policy
+    fn close_gap(&mut self, metric: &LocationMetric) -> Option<GapPolicy> {
+        if !self.segment_open {
+            return None;
+        }
+        let start = self.segments.last()?.end_time()?;
+        let duration = metric.timestamp - start;
+        if duration <= self.config.gaps.threshold {
+            return None;
+        }
+        let reason = self.gap_reason.reason();
+        let policy = self.config.gaps.policy_for(duration, reason);
+        info!("Signal lost for {:.0}s ({}), {:?}", duration, reason, policy);
+        self.gaps.push(Gap {
+            start,
+            end: metric.timestamp,
+            reason,
+            policy,
+        });
+        Some(policy)
+    }
+
+    pub fn has_initial_metric(&self) -> bool {
+        self.initial_metric.is_some()
+    }
+
+    pub fn gps_online(&self) -> bool {
+        self.available_since.is_some()
+    }
+
+    pub fn is_good_accuracy(&self) -> bool {
+        self.gps_acc_good
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Session stopped recording because no movement was detected
+    pub fn is_auto_paused(&self) -> bool {
+        !self.paused && self.auto_pause.is_paused()
+    }
+
+    /// Events not yet taken by the UI
+    pub fn pending_events(&self) -> &[SessionEvent] {
+        &self.events
+    }
+
+    /// Takes all events produced since the previous call
+    pub fn take_events(&mut self) -> Vec<SessionEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn avg_speed(&self) -> f64 {
+        if self.total_time == 0.0 {
+            return 0.0;
+        }
+        self.total_distance / self.total_time
+    }
+
+    /// Total ascent in meters, 0 if the provider does not report altitude
+    pub fn elevation_gain(&self) -> f64 {
+        self.elevation.gain()
+    }
+
+    /// Total descent in meters, positive
+    pub fn elevation_loss(&self) -> f64 {
+        self.elevation.loss()
+    }
+
+    /// Estimated energy expenditure over the recorded time for the stored activity, kcal
+    pub fn calories(&self, profile: &UserProfile) -> f64 {
+        let net_met_seconds = self.effort.net_met_seconds(self.recorded_activity());
+        profile.calories(net_met_seconds, self.total_time, self.elevation.gain())
+    }
+
+    pub fn altitude(&self) -> Option<f64> {
+        self.elevation.altitude()
+    }
+
+    /// Seconds per kilometer at average speed, 0 if not moved yet
+    pub fn avg_pace(&self) -> f64 {
+        laps::pace(self.total_distance, self.total_time)
+    }
+
+    /// Smoothed speed over the last few seconds, m/s. 0 while paused.
+    pub fn current_speed(&self) -> f64 {
+        if self.paused || self.is_auto_paused() {
+            return 0.0;
+        }
+        self.speed.speed()
+    }
+
+    /// Seconds per kilometer at current speed, 0 if standing still
+    pub fn current_pace(&self) -> f64 {
+        let speed = self.current_speed();
+        if speed < 0.01 {
+            return 0.0;
+        }
+        1000.0 / speed
+    }
+
+    /// Time between accepted fixes of all segments, seconds
+    pub fn total_time(&self) -> f64 {
+        self.total_time
+    }
+
+    /// Recorded time spent moving, stops and gaps below `moving_speed` excluded, seconds
+    pub fn moving_time(&self) -> f64 {
+        self.moving_time
+    }
+
+    /// Wall-clock time since the first accepted fix, including pauses and signal loss, seconds
+    pub fn elapsed_time(&self) -> f64 {
+        self.estimated_now().map_or(0.0, |now| self.elapsed_time_at(now))
+    }
+
+    /// Same as [`Self::elapsed_time`] at `now` in the timebase of the location events
+    pub fn elapsed_time_at(&self, now: f64) -> f64 {
+        if let Some(elapsed) = self.elapsed_before_restart {
+            return elapsed;
+        }
+        self.elapsed_anchor.map_or(0.0, |(elapsed, since)| elapsed + (now - since).max(0.0))
+    }
+
+    pub fn total_distance(&self) -> f64 {
+        self.total_distance
+    }
+
+    /// Signal losses closed by a fix so far
+    pub fn gaps(&self) -> &[Gap] {
+        &self.gaps
+    }
+
+    /// Signal loss in progress at `now` as (seconds since the last accepted fix, reason)
+    pub fn current_gap(&self, now: f64) -> Option<(f64, GapReason)> {
+        if !self.segment_open || self.paused {
+            return None;
+        }
+        let duration = now - self.segments.last()?.end_time()?;
+        (duration > self.config.gaps.threshold).then(|| (duration, self.gap_reason.reason()))
+    }
+
+    /// Current time in the timebase of the location events, extrapolated from the latest event
+    pub fn estimated_now(&self) -> Option<f64> {
+        self.clock.map(|(timestamp, at)| timestamp + at.elapsed().as_secs_f64())
+    }
+
+    /// Mapping from the event timebase to UTC, known once a fix reports UTC
+    pub fn utc_clock(&self) -> Option<ClockMapping> {
+        self.utc_clock
+    }
+
+    /// UTC seconds of `timestamp` in the timebase of the location events
+    pub fn utc_at(&self, timestamp: f64) -> Option<f64> {
+        self.utc_clock.map(|clock| clock.utc(timestamp))
+    }
+
+    /// UTC seconds of the first accepted fix
+    pub fn start_utc(&self) -> Option<f64> {
+        let start = self.initial_metric.as_ref()?;
+        start.utc_time.or_else(|| self.utc_at(start.timestamp))
+    }
+
+    /// UTC seconds of the latest event
+    pub fn end_utc(&self) -> Option<f64> {
+        self.utc_at(self.clock?.0)
+    }
+
+    /// Recorded segments in chronological order, one per start/resume
+    pub fn segments(&self) -> &[TrackSegment] {
+        &self.segments
+    }
+
+    /// Accepted fixes of all segments as reported by the location source
+    pub fn raw_track(&self) -> impl Iterator<Item = &LocationMetric> {
+        self.segments.iter().flat_map(|s| s.raw.iter())
+    }
+
+    /// Accepted fixes of all segments after Kalman smoothing, empty if smoothing is disabled
+    pub fn filtered_track(&self) -> impl Iterator<Item = &LocationMetric> {
+        self.segments.iter().flat_map(|s| s.filtered.iter())
+    }
+
+    /// Track of every segment as (north, east) offsets from the start, simplified to the display tolerance
+    pub fn display_track(&self) -> Vec<Vec<(f64, f64)>> {
+        self.segments.iter().map(|segment| {
+            let points: Vec<(f64, f64)> = segment.display.iter().map(|m| (m.latitude, m.longitude)).collect();
+            douglas_peucker(&points, self.config.simplify.display_tolerance).into_iter().map(|i| points[i]).collect()
+        }).collect()
+    }
+
+    /// Fixes of every segment (smoothed if enabled) simplified to the storage tolerance.
+    /// Full resolution stays available in [`Self::raw_track`].
+    pub fn storage_track(&self) -> Vec<Vec<LocationMetric>> {
+        let Some(projection) = self.projection else {
+            return Vec::new();
+        };
+        self.segments.iter().map(|segment| {
+            let fixes = segment.points();
+            let points: Vec<(f64, f64)> = fixes.iter().map(|m| projection.project(m.latitude, m.longitude)).collect();
+            douglas_peucker(&points, self.config.simplify.storage_tolerance).into_iter().map(|i| fixes[i].clone()).collect()
+        }).collect()
+    }
+
+    pub fn is_smoothing_enabled(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Enables or disables Kalman smoothing. Takes effect from the next fix, which opens a new segment
+    /// so distance is never measured between a smoothed and a raw point.
+    pub fn set_smoothing(&mut self, enabled: bool) {
+        self.config.smoothing = enabled;
+        if enabled == self.filter.is_some() {
+            return;
+        }
+        self.filter = enabled.then(KalmanFilter::default);
+        if self.segment_open {
+            info!("Smoothing {}, closing segment {}", if enabled { "enabled" } else { "disabled" }, self.segments.len() - 1);
+            self.segment_open = false;
+        }
+    }
+
+    /// Finished splits, one per `split_distance` meters
+    pub fn splits(&self) -> &[Lap] {
+        self.laps.splits()
+    }
+
+    /// Finished manual and start point laps
+    pub fn laps(&self) -> &[Lap] {
+        self.laps.laps()
+    }
+
+    /// (distance, duration) of the lap in progress
+    pub fn current_lap(&self) -> (f64, f64) {
+        self.laps.current_lap(self.total_distance, self.total_time)
+    }
+
+    pub fn goal(&self) -> Option<Goal> {
+        self.goal.as_ref().map(GoalTracker::goal)
+    }
+
+    /// Progress towards the goal at the current totals
+    pub fn goal_progress(&self) -> Option<GoalProgress> {
+        self.goal.as_ref().map(|goal| goal.progress(self.total_distance, self.total_time))
+    }
+
+    /// Goal was reached, false for sessions without a goal
+    pub fn goal_met(&self) -> bool {
+        self.goal.as_ref().is_some_and(GoalTracker::is_met)
+    }
+
+    pub fn workout(&self) -> Option<&WorkoutPlan> {
+        self.workout.as_ref().map(WorkoutTracker::plan)
+    }
+
+    /// Workout step in progress, `None` without a workout or once all steps are done
+    pub fn workout_step(&self) -> Option<StepProgress> {
+        self.workout.as_ref()?.progress(self.total_distance, self.total_time)
+    }
+
+    /// Lead over the raced ghost at the current totals, `None` without a ghost
+    pub fn ghost_gap(&self) -> Option<GhostGap> {
+        let ghost = self.config.ghost.as_ref()?;
+        Some(ghost.curve.compare(self.total_distance, self.total_time))
+    }
+
+    /// Planned route of the session
+    pub fn route(&self) -> Option<&RouteTracker> {
+        self.route.as_ref()
+    }
+
+    /// Progress along the planned route, `None` without a route or before the first fix
+    pub fn route_progress(&self) -> Option<RouteProgress> {
+        self.route.as_ref()?.progress()
+    }
+
+    /// Finished workout steps, empty for sessions without a workout
+    pub fn step_results(&self) -> &[StepResult] {
+        self.workout.as_ref().map_or(&[], WorkoutTracker::results)
+    }
+
+    /// Closes the lap in progress, triggered by the user
+    pub fn mark_lap(&mut self) {
+        self.laps.finish_lap(LapTrigger::Manual, self.total_distance, self.total_time, &mut self.events);
+    }
+
+    /// Activity the session was started for
+    pub fn activity(&self) -> ActivityType {
+        self.config.activity
+    }
+
+    /// Activity guessed from the recorded speeds, `None` until enough movement was recorded
+    pub fn classified_activity(&self) -> Option<ActivityType> {
+        activity::classify(&self.speed_samples, self.elevation.gain(), self.total_distance)
+    }
+
+    /// Activity to store with the finished session
+    pub fn recorded_activity(&self) -> ActivityType {
+        if self.config.auto_classify {
+            self.classified_activity().unwrap_or(self.config.activity)
+        }
+        else {
+            self.config.activity
+        }
+    }
+
+    pub fn config(&self) -> &TrackingConfig {
+        &self.config
+    }
+
+    /// Accuracy ceiling applied to the next fix
+    pub fn accuracy_ceiling(&self) -> f64 {
+        self.accuracy_gate.ceiling(&self.config)
+    }
+
+    /// Why the most recent fix was rejected, `None` if it was accepted
+    pub fn last_rejection(&self) -> Option<FixRejection> {
+        self.last_rejection
+    }
+
+    pub fn rejected_fixes(&self) -> usize {
+        self.diagnostics.rejected()
+    }
+
+    pub fn diagnostics(&self) -> &SessionDiagnostics {
+        &self.diagnostics
+    }
+
+    pub fn get_last_known_acc(&self) -> Option<f64> {
+        self.last_known_acc
+    }
+
+    /// Closes the current segment. Recorded track is kept, fixes are ignored until [`Self::resume`].
+    pub fn pause(&mut self) {
+        if !self.paused {
+            info!("Session paused, closing segment {}", self.segments.len().saturating_sub(1));
+        }
+        self.record_held_fix();
+        self.paused = true;
+        self.segment_open = false;
+        // movement during the pause is not an outlier
+        self.outliers.reset();
+        self.gps_acc_good = false;
+        self.reset_auto_pause();
+    }
+
+    /// Next accepted fix opens a new segment
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.reset_auto_pause();
+    }
+
+    /// Location events continue in a new timebase, e.g. the monotonic clock of a new process after a
+    /// recovery. The open segment is closed and everything tied to the old timestamps starts over,
+    /// the time between the last old and the first new event counts toward nothing.
+    pub fn restart_clock(&mut self) {
+        info!("Event timebase restarted, closing segment {}", self.segments.len().saturating_sub(1));
+        if let Some(start) = &mut self.initial_metric {
+            start.utc_time = start.utc_time.or_else(|| self.utc_clock.map(|clock| clock.utc(start.timestamp)));
+        }
+        if let Some((timestamp, _)) = self.clock {
+            self.elapsed_before_restart = Some(self.elapsed_time_at(timestamp));
+        }
+        self.record_held_fix();
+        self.clock = None;
+        self.utc_clock = None;
+        self.available_since = None;
+        self.segment_open = false;
+        self.outliers.reset();
+        self.gps_acc_good = false;
+        self.reset_auto_pause();
+    }
+
+    /// Records a held jump before its segment closes, no following fix will tell whether it was a spike
+    fn record_held_fix(&mut self) {
+        let Some(((metric, fix), projection)) = self.held_fix.take().zip(self.projection) else {
+            return;
+        };
+        info!("Recording unconfirmed jump at {}", metric.timestamp);
+        self.diagnostics.unconfirmed_jumps += 1;
+        self.outliers.accept(fix);
+        let _ = self.record_fix(metric, projection);
+    }
+
+    fn reset_auto_pause(&mut self) {
+        if self.auto_pause.is_paused() {
+            self.events.push(SessionEvent::AutoResumed);
+        }
+        self.auto_pause.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{drain_source, LocationSource};
+    use crate::tracking::gaps::GapConfig;
+    use crate::tracking::sources::synthetic::SyntheticSource;
+
+    fn session() -> TrackingSession {
+        TrackingSession::with_config(TrackingConfig { warm_up: 0.0, smoothing: false, ..TrackingConfig::default() })
+    }
+
+    /// Feeds fixes given as (timestamp, north, east) with 3 m accuracy
+    fn feed(session: &mut TrackingSession, fixes: &[(f64, f64, f64)]) -> Vec<Result<(), FixRejection>> {
+        let origin = LocalProjection::new(55.75, 37.61);
+        fixes.iter().map(|&(timestamp, north, east)| {
+            let (lat, lon) = origin.unproject(north, east);
+            session.handle_event(LocationEvent::Fix(LocationMetric::new(lat, lon, 3.0, timestamp)))
+        }).collect()
+    }
+
+    #[test]
+    fn smoothing_shortens_jittered_line() {
+        let distance = |smoothing: bool| {
+            let config = TrackingConfig { warm_up: 0.0, smoothing, ..TrackingConfig::default() };
+            let mut session = TrackingSession::with_config(config);
+            let mut source = SyntheticSource::new((55.75, 37.61), 1.5, 30.0, 300).accuracy(5.0).jitter(3.0, 7);
+            drain_source(&mut session, &mut source);
+            session.total_distance()
+        };
+        let (raw, smoothed) = (distance(false), distance(true));
+        // 299 s at 1.5 m/s
+        assert!(smoothed <= raw, "{} > {}", smoothed, raw);
+        assert!((smoothed - 448.5).abs() < (raw - 448.5).abs());
+    }
+
+    #[test]
+    fn toggling_opens_segment() {
+        let config = TrackingConfig { warm_up: 0.0, smoothing: true, ..TrackingConfig::default() };
+        let mut session = TrackingSession::with_config(config);
+        let mut source = SyntheticSource::new((55.75, 37.61), 2.0, 0.0, 200);
+        for _ in 0..100 {
+            let _ = session.handle_event(source.next_event().unwrap());
+        }
+        session.set_smoothing(false);
+        drain_source(&mut session, &mut source);
+
+        let segments = session.segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].filtered.len(), segments[0].raw.len());
+        assert!(segments[1].filtered.is_empty());
+        // one step is lost at the switch
+        assert!((session.total_distance() - 396.0).abs() < 1.0, "{}", session.total_distance());
+    }
+
+    #[test]
+    fn spike_is_held_then_dropped() {
+        let mut session = session();
+        // walking north every 2 s, one fix jumps 25 m east
+        let mut fixes: Vec<_> = (0..10).map(|i| (i as f64 * 2.0, i as f64 * 3.0, 0.0)).collect();
+        fixes[5].2 = 25.0;
+        let results = feed(&mut session, &fixes);
+
+        assert_eq!(results[5], Err(FixRejection::Held));
+        assert!(results.iter().enumerate().all(|(i, result)| i == 5 || result.is_ok()));
+        assert_eq!(session.diagnostics().spikes, 1);
+        assert_eq!(session.raw_track().count(), 9);
+        assert!((session.total_distance() - 27.0).abs() < 0.1, "{}", session.total_distance());
+    }
+
+    #[test]
+    fn fast_segment_is_accepted() {
+        let mut session = session();
+        // walking, then riding off at 8 m/s, every jump is long enough to be held
+        let mut fixes: Vec<_> = (0..5).map(|i| (i as f64 * 3.0, i as f64 * 4.5, 0.0)).collect();
+        fixes.extend((1..=10).map(|i| (12.0 + i as f64 * 3.0, 18.0 + i as f64 * 24.0, 0.0)));
+        let results = feed(&mut session, &fixes);
+
+        assert!(results[5..].iter().all(|result| *result == Err(FixRejection::Held)));
+        assert_eq!(session.diagnostics().spikes, 0);
+        assert_eq!(session.rejected_fixes(), 0);
+        // the last one waits for a fix that never comes
+        assert_eq!(session.raw_track().count(), 14);
+        assert!((session.total_distance() - 18.0 - 9.0 * 24.0).abs() < 0.1, "{}", session.total_distance());
+
+        // until the segment closes
+        session.pause();
+        assert_eq!(session.raw_track().count(), 15);
+        assert!((session.total_distance() - 18.0 - 10.0 * 24.0).abs() < 0.1, "{}", session.total_distance());
+        assert_eq!(session.diagnostics().unconfirmed_jumps, 1);
+        session.pause();
+        assert_eq!(session.diagnostics().unconfirmed_jumps, 1);
+    }
+
+    #[test]
+    fn diagnostics_count_rejections() {
+        let mut session = session();
+        let mut fixes: Vec<_> = (0..12).map(|i| (i as f64, i as f64 * 1.5, 0.0)).collect();
+        // teleport by 200 m, then a sudden sprint
+        fixes[4].2 = 200.0;
+        fixes[8].1 += 14.0;
+        let results = feed(&mut session, &fixes);
+
+        assert!(matches!(results[4], Err(FixRejection::ImplausibleSpeed { .. })));
+        assert!(matches!(results[8], Err(FixRejection::ImplausibleAcceleration { .. })));
+        let diagnostics = session.diagnostics();
+        assert_eq!((diagnostics.implausible_speed, diagnostics.implausible_acceleration), (1, 1));
+        assert_eq!(diagnostics.accepted, 10);
+        assert_eq!(session.rejected_fixes(), 2);
+    }
+
+    /// Walks north at 1.5 m/s with no fixes between 20 s and 40 s, `during` is fed inside the dropout
+    fn walk_with_dropout(gaps: GapConfig, during: &[LocationEvent]) -> TrackingSession {
+        let mut session = TrackingSession::with_config(TrackingConfig { warm_up: 0.0, smoothing: false, gaps, ..TrackingConfig::default() });
+        let before: Vec<_> = (0..=20).map(|t| (t as f64, t as f64 * 1.5, 0.0)).collect();
+        let after: Vec<_> = (40..=60).map(|t| (t as f64, t as f64 * 1.5, 0.0)).collect();
+        feed(&mut session, &before);
+        for event in during {
+            let _ = session.handle_event(event.clone());
+        }
+        feed(&mut session, &after);
+        session
+    }
+
+    fn gap_policy(policy: GapPolicy) -> GapConfig {
+        GapConfig { policy, ..GapConfig::default() }
+    }
+
+    #[test]
+    fn gap_policies() {
+        let bridged = walk_with_dropout(gap_policy(GapPolicy::Bridge), &[]);
+        let excluded = walk_with_dropout(gap_policy(GapPolicy::Exclude), &[]);
+        let interpolated = walk_with_dropout(gap_policy(GapPolicy::Interpolate), &[]);
+
+        for (session, policy) in [(&bridged, GapPolicy::Bridge), (&excluded, GapPolicy::Exclude), (&interpolated, GapPolicy::Interpolate)] {
+            assert_eq!(session.gaps(), &[Gap { start: 20.0, end: 40.0, reason: GapReason::NoFixes, policy }]);
+        }
+
+        // the straight line across the gap counts
+        assert!((bridged.total_distance() - 90.0).abs() < 0.1, "{}", bridged.total_distance());
+        assert_eq!((bridged.total_time(), bridged.moving_time(), bridged.segments().len()), (60.0, 60.0, 1));
+        assert_eq!(bridged.segments()[0].display.len(), 42);
+
+        assert!((excluded.total_distance() - 60.0).abs() < 0.1, "{}", excluded.total_distance());
+        assert_eq!((excluded.total_time(), excluded.moving_time(), excluded.segments().len()), (40.0, 40.0, 2));
+
+        assert!((interpolated.total_distance() - bridged.total_distance()).abs() < 1e-9);
+        assert_eq!((interpolated.total_time(), interpolated.moving_time(), interpolated.segments().len()), (60.0, 60.0, 1));
+        // one point per second in between, on the line
+        let display = &interpolated.segments()[0].display;
+        assert_eq!(display.len(), 42 + 19);
+        for (i, point) in display[21..40].iter().enumerate() {
+            let t = 21.0 + i as f64;
+            assert!((point.timestamp - t).abs() < 1e-9);
+            assert!((point.latitude - t * 1.5).abs() < 0.05 && point.longitude.abs() < 0.05, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn gap_reasons() {
+        let origin = LocalProjection::new(55.75, 37.61);
+        let fix = |timestamp: f64, north: f64, east: f64, accuracy: f64| {
+            let (lat, lon) = origin.unproject(north, east);
+            LocationEvent::Fix(LocationMetric::new(lat, lon, accuracy, timestamp))
+        };
+        let reason = |session: &TrackingSession| (session.gaps()[0].reason, session.gaps()[0].policy);
+        let interpolate = gap_policy(GapPolicy::Interpolate);
+
+        let session = walk_with_dropout(interpolate.clone(), &[fix(30.0, 45.0, 0.0, 30.0)]);
+        assert_eq!(reason(&session), (GapReason::PoorAccuracy, GapPolicy::Interpolate));
+
+        let session = walk_with_dropout(interpolate.clone(), &[fix(30.0, 45.0, 500.0, 3.0)]);
+        assert_eq!(reason(&session), (GapReason::Outliers, GapPolicy::Interpolate));
+
+        // turning the provider off outweighs the rejections around it and always excludes
+        let events = [LocationEvent::ProviderDisabled(25.0), LocationEvent::ProviderEnabled(35.0), fix(36.0, 54.0, 0.0, 30.0)];
+        let session = walk_with_dropout(interpolate.clone(), &events);
+        assert_eq!(reason(&session), (GapReason::ProviderDisabled, GapPolicy::Exclude));
+        assert_eq!(session.segments().len(), 2);
+
+        let session = walk_with_dropout(GapConfig { exclude_after: 15.0, ..interpolate }, &[]);
+        assert_eq!(reason(&session), (GapReason::NoFixes, GapPolicy::Exclude));
+    }
+
+    #[test]
+    fn current_speed_and_pace() {
+        let mut session = session();
+        let fixes: Vec<_> = (0..30).map(|t| (t as f64, t as f64 * 2.5, 0.0)).collect();
+        feed(&mut session, &fixes);
+        assert!((session.current_speed() - 2.5).abs() < 0.01, "{}", session.current_speed());
+        assert!((session.current_pace() - 400.0).abs() < 1.0, "{}", session.current_pace());
+
+        session.pause();
+        assert_eq!((session.current_speed(), session.current_pace()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn stop_counts_toward_elapsed_but_not_moving_time() {
+        let mut session = session();
+        // 30 s walking, 30 s standing, 30 s walking
+        let fixes: Vec<_> = (0..=90).map(|t: i32| (t as f64, (t.min(30) + (t - 60).max(0)) as f64 * 1.5, 0.0)).collect();
+        feed(&mut session, &fixes);
+
+        assert_eq!(session.total_time(), 90.0);
+        assert_eq!(session.elapsed_time_at(90.0), 90.0);
+        // the current speed trails the stop by a few seconds at both ends
+        let moving = session.moving_time();
+        assert!(moving > 55.0 && moving < 70.0, "{}", moving);
+
+        // elapsed time keeps running without fixes, paused or not
+        assert_eq!(session.elapsed_time_at(150.0), 150.0);
+        session.pause();
+        assert_eq!(session.elapsed_time_at(200.0), 200.0);
+        assert_eq!(session.moving_time(), moving);
+    }
+
+    #[test]
+    fn pause_splits_segments() {
+        let mut session = session();
+        let walk = |from: i32, to: i32| (from..=to).map(|t| (t as f64, t as f64 * 1.5, 0.0)).collect::<Vec<_>>();
+        feed(&mut session, &walk(0, 20));
+        let moving = session.moving_time();
+        session.pause();
+        assert!(feed(&mut session, &walk(21, 40)).iter().all(|result| *result == Err(FixRejection::Paused)));
+        assert_eq!(session.segments().len(), 1);
+        assert_eq!(session.moving_time(), moving);
+        session.resume();
+        feed(&mut session, &walk(41, 60));
+
+        let segments = session.segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].end_time(), segments[1].start_time()), (Some(20.0), Some(41.0)));
+        // the 31.5 m walked while paused count toward nothing
+        assert!((session.total_distance() - 39.0 * 1.5).abs() < 0.1, "{}", session.total_distance());
+        assert_eq!(session.total_time(), 39.0);
+        assert!(session.moving_time() <= 39.0);
+        assert_eq!(session.diagnostics().rejected(), 0);
+    }
+}
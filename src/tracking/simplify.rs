@@ -0,0 +1,86 @@
+/// Tolerances for reducing the number of track points, meters
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SimplifyConfig {
+    /// polyline drawn on screen
+    pub display_tolerance: f64,
+    /// polyline stored with the record
+    pub storage_tolerance: f64,
+}
+
+impl Default for SimplifyConfig {
+    fn default() -> Self {
+        SimplifyConfig {
+            display_tolerance: 5.0,
+            storage_tolerance: 1.0,
+        }
+    }
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (a.0 + dx * t, a.1 + dy * t);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
+
+/// Douglas-Peucker simplification of planar points. Returns indices of the kept points in order,
+/// the first and the last point are always kept.
+pub fn douglas_peucker(points: &[(f64, f64)], tolerance: f64) -> Vec<usize> {
+    if points.len() <= 2 {
+        return (0..points.len()).collect();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // explicit stack, long tracks would overflow a recursive implementation
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+    (0..points.len()).filter(|&i| keep[i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerance_and_endpoints() {
+        // straight line with sub-meter wobble
+        let line: Vec<_> = (0..100).map(|i| (i as f64, if i % 2 == 0 { 0.3 } else { -0.3 })).collect();
+        assert_eq!(douglas_peucker(&line, 1.0), vec![0, 99]);
+        assert_eq!(douglas_peucker(&line, 0.1).len(), 100);
+
+        // a corner of 5 m is kept only below that tolerance
+        let corner = [(0.0, 0.0), (10.0, 1.0), (20.0, 5.0), (30.0, 1.0), (40.0, 0.0)];
+        assert_eq!(douglas_peucker(&corner, 2.0), vec![0, 2, 4]);
+        assert_eq!(douglas_peucker(&corner, 5.0), vec![0, 4]);
+        assert_eq!(douglas_peucker(&corner[..2], 100.0), vec![0, 1]);
+        assert!(douglas_peucker(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn closed_loop() {
+        // first and last point coincide, distances are measured to that point
+        let square = [(0.0, 0.0), (0.0, 10.0), (0.1, 10.0), (10.0, 10.0), (10.0, 0.0), (0.0, 0.0)];
+        assert_eq!(douglas_peucker(&square, 1.0), vec![0, 1, 3, 4, 5]);
+        assert_eq!(douglas_peucker(&square, 20.0), vec![0, 5]);
+    }
+}
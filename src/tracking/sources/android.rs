@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use jni::JNIEnv;
+use jni::objects::JClass;
+use jni::sys::jdouble;
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use crate::tracking::{LocationEvent, LocationMetric, LocationSource, RECORDER};
+
+/// Events delivered by `LocationHelper` that are not yet consumed by the session
+static PENDING_EVENTS: Mutex<VecDeque<LocationEvent>> = Mutex::new(VecDeque::new());
+
+/// Location source backed by the Android `LocationManager` (see `LocationHelper.java`)
+pub struct AndroidLocationSource;
+
+impl LocationSource for AndroidLocationSource {
+    fn next_event(&mut self) -> Option<LocationEvent> {
+        PENDING_EVENTS.lock().pop_front()
+    }
+}
+
+fn push_event(event: LocationEvent) {
+    PENDING_EVENTS.lock().push_back(event);
+
+    RECORDER.lock().drain(&mut AndroidLocationSource);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onLocationUpdate(
+    _env: JNIEnv,
+    _class: JClass,
+    latitude: jdouble,
+    longitude: jdouble,
+    acc: jdouble,
+    timestamp: jdouble,
+    utc_time: jdouble,
+    altitude: jdouble,
+    vertical_acc: jdouble,
+    speed: jdouble,
+    speed_acc: jdouble,
+    bearing: jdouble
+) {
+    debug!("Location update at {}: lat {}, lon {}, acc {}", timestamp, latitude, longitude, acc);
+    let mut metric = LocationMetric::new(latitude, longitude, acc, timestamp);
+    // missing values are passed as NaN
+    if !utc_time.is_nan() {
+        metric = metric.with_utc_time(utc_time);
+    }
+    if !altitude.is_nan() {
+        metric = metric.with_altitude(altitude, (!vertical_acc.is_nan()).then_some(vertical_acc));
+    }
+    if !speed.is_nan() {
+        metric = metric.with_speed(speed, (!speed_acc.is_nan()).then_some(speed_acc));
+    }
+    if !bearing.is_nan() {
+        metric = metric.with_bearing(bearing);
+    }
+    push_event(LocationEvent::Fix(metric));
+}
+
+
+#[no_mangle]
+pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onProviderEnabled(
+    _env: JNIEnv,
+    _class: JClass,
+    timestamp: jdouble
+) {
+    info!("GPS provider enabled!");
+    push_event(LocationEvent::ProviderEnabled(timestamp));
+}
+
+
+#[no_mangle]
+pub extern "system" fn Java_com_skygrel_panther_LocationHelper_onProviderDisabled(
+    _env: JNIEnv,
+    _class: JClass,
+    timestamp: jdouble
+) {
+    warn!("GPS provider disabled!");
+    push_event(LocationEvent::ProviderDisabled(timestamp));
+}
@@ -0,0 +1,264 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use crate::tracking::{LocationEvent, LocationMetric, LocationSource};
+use crate::tracking::sources::{hdop_accuracy, parse_iso8601};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:2947";
+
+/// Asks gpsd to stream JSON reports
+const WATCH_COMMAND: &str = "?WATCH={\"enable\":true,\"json\":true};\n";
+
+/// Accuracy of fixes without `eph` and before the first SKY report
+const DEFAULT_ACCURACY: f64 = 5.0;
+
+/// Satellite status from the latest SKY report
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SkyStatus {
+    pub hdop: Option<f64>,
+    pub visible: usize,
+    pub used: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "class")]
+enum Report {
+    #[serde(rename = "TPV")]
+    Tpv(Tpv),
+    #[serde(rename = "SKY")]
+    Sky(Sky),
+    #[serde(other)]
+    Other,
+}
+
+/// Time-position-velocity report, only the fields a fix needs
+#[derive(Deserialize)]
+struct Tpv {
+    #[serde(default)]
+    mode: u8,
+    time: Option<String>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    /// height above the ellipsoid, gpsd 3.20+
+    #[serde(rename = "altHAE")]
+    alt_hae: Option<f64>,
+    /// older gpsd, above the ellipsoid as well
+    alt: Option<f64>,
+    eph: Option<f64>,
+    epv: Option<f64>,
+    speed: Option<f64>,
+    eps: Option<f64>,
+    track: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Sky {
+    hdop: Option<f64>,
+    #[serde(default)]
+    satellites: Vec<Satellite>,
+}
+
+#[derive(Deserialize)]
+struct Satellite {
+    #[serde(default)]
+    used: bool,
+}
+
+/// Location source reading a gpsd daemon, for desktop builds and bench tests with a USB receiver.
+///
+/// A reader thread turns the JSON reports into events, `next_event` never blocks.
+/// TPV reports with at least a 2D fix become fixes timestamped with their UTC time,
+/// SKY reports provide the HDOP used when `eph` is missing.
+pub struct GpsdSource {
+    events: Receiver<LocationEvent>,
+    sky: Arc<Mutex<SkyStatus>>,
+    finished: bool,
+}
+
+impl GpsdSource {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        info!("Connected to gpsd at {}", stream.peer_addr()?);
+        stream.write_all(WATCH_COMMAND.as_bytes())?;
+
+        let (sender, events) = mpsc::channel();
+        let sky = Arc::new(Mutex::new(SkyStatus::default()));
+        let reader_sky = sky.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    warn!("gpsd connection failed");
+                    break;
+                };
+                if let Some(event) = parse_report(&line, &reader_sky) {
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+            info!("gpsd connection closed");
+        });
+
+        Ok(GpsdSource {
+            events,
+            sky,
+            finished: false,
+        })
+    }
+
+    pub fn sky(&self) -> SkyStatus {
+        self.sky.lock().clone()
+    }
+}
+
+impl LocationSource for GpsdSource {
+    fn next_event(&mut self) -> Option<LocationEvent> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Reads one line of the gpsd stream, returns a fix for a usable TPV report
+fn parse_report(line: &str, sky: &Mutex<SkyStatus>) -> Option<LocationEvent> {
+    let report = match serde_json::from_str(line) {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("Bad gpsd report {}: {}", line, e);
+            return None;
+        }
+    };
+    match report {
+        Report::Tpv(tpv) => tpv_to_fix(tpv, sky.lock().hdop).map(LocationEvent::Fix),
+        Report::Sky(report) => {
+            let mut sky = sky.lock();
+            // gpsd sends partial SKY reports without satellites in between
+            if report.hdop.is_some() {
+                sky.hdop = report.hdop;
+            }
+            if !report.satellites.is_empty() {
+                sky.visible = report.satellites.len();
+                sky.used = report.satellites.iter().filter(|s| s.used).count();
+            }
+            None
+        }
+        Report::Other => None,
+    }
+}
+
+fn tpv_to_fix(tpv: Tpv, hdop: Option<f64>) -> Option<LocationMetric> {
+    // 0 and 1 mean no fix
+    if tpv.mode < 2 {
+        return None;
+    }
+    let timestamp = parse_iso8601(tpv.time.as_deref()?)?;
+    let accuracy = tpv.eph.or(hdop.map(hdop_accuracy)).unwrap_or(DEFAULT_ACCURACY);
+
+    let mut metric = LocationMetric::new(tpv.lat?, tpv.lon?, accuracy, timestamp).with_utc_time(timestamp);
+    if tpv.mode == 3 {
+        if let Some(altitude) = tpv.alt_hae.or(tpv.alt) {
+            metric = metric.with_altitude(altitude, tpv.epv);
+        }
+    }
+    if let Some(speed) = tpv.speed {
+        metric = metric.with_speed(speed, tpv.eps);
+    }
+    if let Some(track) = tpv.track {
+        metric = metric.with_bearing(track);
+    }
+    Some(metric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    /// Accepts one client, checks it asks for a watch and sends `reports` line by line
+    fn fake_gpsd(reports: Vec<&'static str>) -> (String, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"{\"class\":\"VERSION\",\"release\":\"3.25\",\"proto_major\":3,\"proto_minor\":15}\n").unwrap();
+            let mut command = String::new();
+            BufReader::new(stream.try_clone().unwrap()).read_line(&mut command).unwrap();
+            assert!(command.starts_with("?WATCH={\"enable\":true"));
+            for report in reports {
+                stream.write_all(report.as_bytes()).unwrap();
+                stream.write_all(b"\n").unwrap();
+            }
+        });
+        (address, handle)
+    }
+
+    fn collect(source: &mut GpsdSource) -> Vec<LocationEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = Vec::new();
+        while !source.is_finished() && Instant::now() < deadline {
+            match source.next_event() {
+                Some(event) => events.push(event),
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn fixes_from_fake_gpsd() {
+        let (address, server) = fake_gpsd(vec![
+            r#"{"class":"DEVICES","devices":[{"class":"DEVICE","path":"/dev/ttyACM0","activated":"2024-05-01T10:00:00.000Z"}]}"#,
+            r#"{"class":"WATCH","enable":true,"json":true}"#,
+            r#"{"class":"TPV","device":"/dev/ttyACM0","mode":1,"time":"2024-05-01T10:00:00.000Z"}"#,
+            r#"{"class":"SKY","device":"/dev/ttyACM0","hdop":1.2,"satellites":[{"PRN":5,"used":true},{"PRN":7,"used":true},{"PRN":9,"used":false}]}"#,
+            r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"time":"2024-05-01T10:00:01.000Z","lat":55.75,"lon":37.61,"altHAE":164.5,"alt":150.5,"eph":3.4,"epv":6.1,"speed":1.4,"eps":0.3,"track":87.5}"#,
+            r#"{"class":"TPV","device":"/dev/ttyACM0","mode":2,"time":"2024-05-01T10:00:02.000Z","lat":55.75001,"lon":37.61003,"alt":150.0}"#,
+            "not json",
+        ]);
+        let mut source = GpsdSource::connect(address).unwrap();
+        let events = collect(&mut source);
+        server.join().unwrap();
+
+        assert!(source.is_finished());
+        assert_eq!(source.sky(), SkyStatus { hdop: Some(1.2), visible: 3, used: 2 });
+        let [LocationEvent::Fix(first), LocationEvent::Fix(second)] = events.as_slice() else {
+            panic!("unexpected events {:?}", events);
+        };
+
+        let time = parse_iso8601("2024-05-01T10:00:01Z").unwrap();
+        let expected = LocationMetric::new(55.75, 37.61, 3.4, time)
+            .with_utc_time(time)
+            .with_altitude(164.5, Some(6.1))
+            .with_speed(1.4, Some(0.3))
+            .with_bearing(87.5);
+        assert_eq!(first, &expected);
+
+        // no eph, accuracy comes from the SKY HDOP. A 2D fix has no usable altitude.
+        assert_eq!(second.accuracy, hdop_accuracy(1.2));
+        assert_eq!(second.timestamp, time + 1.0);
+        assert_eq!(second.altitude, None);
+    }
+
+    #[test]
+    fn connection_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        assert!(GpsdSource::connect(address).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, Read};
+use crate::tracking::LocationMetric;
+use crate::tracking::sources::{hdop_accuracy, parse_iso8601};
+
+/// Accuracy of points that don't carry a dilution of precision
+const DEFAULT_ACCURACY: f64 = 5.0;
+
+/// Reads the track points of a GPX file, in file order.
+///
+/// Only `trkpt` elements are used, with their `ele`, `time`, `hdop` and GPX 1.0 `speed` children.
+/// Timestamps are UTC seconds and carried as the UTC time as well,
+/// points without a time are spaced one second apart and have no UTC time.
+pub fn parse<R: Read>(mut reader: R) -> io::Result<Vec<LocationMetric>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    points(&text, "trkpt")
+}
+
+/// Reads a planned route: the `rtept` points of the file, or its track points if it has no route
+pub fn parse_route<R: Read>(mut reader: R) -> io::Result<Vec<LocationMetric>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let route = points(&text, "rtept")?;
+    if route.is_empty() { points(&text, "trkpt") } else { Ok(route) }
+}
+
+/// Every `<name ...>` point element of `text`
+fn points(text: &str, name: &str) -> io::Result<Vec<LocationMetric>> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut fixes = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start..];
+        let tag_end = rest.find('>').ok_or_else(|| invalid(&format!("Unterminated {} tag", name)))?;
+        let tag = &rest[..tag_end];
+        let (body, next) = if tag.ends_with('/') {
+            ("", &rest[tag_end + 1..])
+        } else {
+            let end = rest.find(&close).ok_or_else(|| invalid(&format!("Missing {}", close)))?;
+            (&rest[tag_end + 1..end], &rest[end + close.len()..])
+        };
+        rest = next;
+
+        let latitude = attribute(tag, "lat").ok_or_else(|| invalid(&format!("Bad lat in {}>", tag)))?;
+        let longitude = attribute(tag, "lon").ok_or_else(|| invalid(&format!("Bad lon in {}>", tag)))?;
+        let utc_time = match element(body, "time") {
+            Some(time) => Some(parse_iso8601(time).ok_or_else(|| invalid(&format!("Bad time {}", time)))?),
+            None => None,
+        };
+        let timestamp = utc_time.unwrap_or_else(|| fixes.last().map_or(0.0, |last: &LocationMetric| last.timestamp + 1.0));
+        let accuracy = element(body, "hdop")
+            .and_then(|hdop| hdop.parse().ok())
+            .map_or(DEFAULT_ACCURACY, hdop_accuracy);
+
+        let mut metric = LocationMetric::new(latitude, longitude, accuracy, timestamp);
+        // made up timestamps are no UTC time
+        if let Some(utc_time) = utc_time {
+            metric = metric.with_utc_time(utc_time);
+        }
+        if let Some(altitude) = element(body, "ele").and_then(|ele| ele.parse().ok()) {
+            metric = metric.with_altitude(altitude, None);
+        }
+        if let Some(speed) = element(body, "speed").and_then(|speed| speed.parse().ok()) {
+            metric = metric.with_speed(speed, None);
+        }
+        fixes.push(metric);
+    }
+    Ok(fixes)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Numeric value of `name="..."` or `name='...'` inside a tag
+fn attribute(tag: &str, name: &str) -> Option<f64> {
+    let mut search = tag;
+    loop {
+        let pos = search.find(name)?;
+        let after = search[pos + name.len()..].trim_start();
+        let preceded_by_space = search[..pos].ends_with(char::is_whitespace);
+        if let Some(value) = after.strip_prefix('=').filter(|_| preceded_by_space) {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            let value = &value[1..];
+            let end = value.find(quote)?;
+            return value[..end].trim().parse().ok();
+        }
+        search = &search[pos + name.len()..];
+    }
+}
+
+/// Text of the first `<name>` child
+fn element<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find(&close)?;
+    Some(body[start..end].trim())
+}
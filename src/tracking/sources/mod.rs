@@ -0,0 +1,77 @@
+pub mod android;
+pub mod gpsd;
+pub mod gpx;
+pub mod nmea;
+pub mod replay;
+pub mod synthetic;
+
+/// Seeded xorshift generator, keeps generated and perturbed tracks reproducible
+#[derive(Clone, Debug)]
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        XorShift(seed.max(1))
+    }
+
+    /// Uniform random value in [0; 1)
+    pub fn next_unit(&mut self) -> f64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform random value in [-1; 1]
+    pub fn next_signed(&mut self) -> f64 {
+        self.next_unit() * 2.0 - 1.0
+    }
+}
+
+/// Seconds since the UNIX epoch of a UTC calendar date and time
+pub(crate) fn utc_timestamp(year: i64, month: u32, day: u32, seconds_of_day: f64) -> f64 {
+    // days from civil, proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    days as f64 * 86400.0 + seconds_of_day
+}
+
+/// UTC seconds of an ISO 8601 date and time, e.g. `2024-05-01T10:00:00.5Z` or `2024-05-01T12:00:00+02:00`
+pub(crate) fn parse_iso8601(time: &str) -> Option<f64> {
+    let (date, time) = time.split_once('T')?;
+    let mut date = date.splitn(3, '-');
+    let year = date.next()?.parse().ok()?;
+    let month = date.next()?.parse().ok()?;
+    let day = date.next()?.parse().ok()?;
+
+    let (clock, offset) = if let Some(clock) = time.strip_suffix('Z') {
+        (clock, 0.0)
+    } else if let Some(pos) = time.rfind(['+', '-']) {
+        let sign = if time[pos..].starts_with('-') { -1.0 } else { 1.0 };
+        let (hours, minutes) = time[pos + 1..].split_once(':').unwrap_or((&time[pos + 1..], "0"));
+        let offset = hours.parse::<f64>().ok()? * 3600.0 + minutes.parse::<f64>().ok()? * 60.0;
+        (&time[..pos], sign * offset)
+    } else {
+        (time, 0.0)
+    };
+    let mut clock = clock.splitn(3, ':');
+    let hours: f64 = clock.next()?.parse().ok()?;
+    let minutes: f64 = clock.next()?.parse().ok()?;
+    let seconds: f64 = clock.next()?.parse().ok()?;
+    Some(utc_timestamp(year, month, day, hours * 3600.0 + minutes * 60.0 + seconds) - offset)
+}
+
+/// Typical range error of a GPS receiver in meters, multiplied by HDOP gives the horizontal accuracy
+const RANGE_ERROR: f64 = 5.0;
+
+/// Horizontal accuracy in meters estimated from the horizontal dilution of precision
+pub(crate) fn hdop_accuracy(hdop: f64) -> f64 {
+    hdop * RANGE_ERROR
+}
@@ -0,0 +1,175 @@
+use std::io::{self, BufRead};
+use log::warn;
+use crate::tracking::LocationMetric;
+use crate::tracking::sources::{hdop_accuracy, utc_timestamp};
+
+/// Accuracy of fixes without a GGA sentence
+const DEFAULT_ACCURACY: f64 = 5.0;
+const KNOTS: f64 = 1852.0 / 3600.0;
+
+/// Sentences sharing the same UTC time
+#[derive(Default)]
+struct Epoch {
+    /// seconds of the day
+    time: f64,
+    position: Option<(f64, f64)>,
+    valid: bool,
+    /// status of RMC, it overrides the GGA fix quality
+    rmc_valid: Option<bool>,
+    hdop: Option<f64>,
+    /// above the ellipsoid
+    altitude: Option<f64>,
+    speed: Option<f64>,
+    bearing: Option<f64>,
+}
+
+/// Reads fixes from an NMEA 0183 log, combining the RMC and GGA sentences of every epoch.
+///
+/// Sentences of any talker are accepted, other types and sentences with a wrong checksum are skipped.
+/// Timestamps are seconds of the day of the first epoch, counting on past midnight, for the whole log.
+/// UTC time is set on the fixes from the first RMC that brings the date.
+pub fn parse<R: BufRead>(reader: R) -> io::Result<Vec<LocationMetric>> {
+    let mut fixes = Vec::new();
+    let mut epoch: Option<Epoch> = None;
+    let mut date: Option<(i64, u32, u32)> = None;
+    let mut last_time = None;
+    let mut day_offset = 0.0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let Some(fields) = sentence_fields(line.trim()) else {
+            if !line.trim().is_empty() {
+                warn!("Skipping bad NMEA sentence at line {}: {}", i + 1, line);
+            }
+            continue;
+        };
+        let kind = fields[0].get(2..).unwrap_or("");
+        if kind != "RMC" && kind != "GGA" {
+            continue;
+        }
+        let Some(time) = fields.get(1).and_then(|t| parse_time_of_day(t)) else {
+            continue;
+        };
+        if epoch.as_ref().is_some_and(|e| e.time != time) {
+            fixes.extend(epoch.take().and_then(|e| e.into_fix(date, day_offset)));
+            // without a date the time of day wraps around at midnight
+            if last_time.is_some_and(|last| time < last - 43200.0) {
+                day_offset += 86400.0;
+            }
+        }
+        last_time = Some(time);
+        if kind == "RMC" {
+            if let Some(d) = fields.get(9).and_then(|d| parse_date(d)) {
+                date = Some(d);
+            }
+        }
+        let epoch = epoch.get_or_insert_with(|| Epoch { time, ..Epoch::default() });
+        if kind == "RMC" {
+            epoch.read_rmc(&fields);
+        } else {
+            epoch.read_gga(&fields);
+        }
+    }
+    fixes.extend(epoch.and_then(|e| e.into_fix(date, day_offset)));
+    Ok(fixes)
+}
+
+impl Epoch {
+    fn read_rmc(&mut self, fields: &[&str]) {
+        self.rmc_valid = Some(fields.get(2) == Some(&"A"));
+        if let Some(position) = parse_position(fields, 3) {
+            self.position = Some(position);
+        }
+        self.speed = fields.get(7).and_then(|s| s.parse::<f64>().ok()).map(|knots| knots * KNOTS);
+        self.bearing = fields.get(8).and_then(|b| b.parse().ok());
+    }
+
+    fn read_gga(&mut self, fields: &[&str]) {
+        self.valid = fields.get(6).and_then(|q| q.parse::<u32>().ok()).unwrap_or(0) > 0;
+        if let Some(position) = parse_position(fields, 2) {
+            self.position = Some(position);
+        }
+        self.hdop = fields.get(8).and_then(|h| h.parse().ok());
+        let altitude = fields.get(9).and_then(|a| a.parse::<f64>().ok());
+        let geoid = fields.get(11).and_then(|g| g.parse::<f64>().ok()).unwrap_or(0.0);
+        self.altitude = altitude.map(|a| a + geoid);
+    }
+
+    fn into_fix(self, date: Option<(i64, u32, u32)>, day_offset: f64) -> Option<LocationMetric> {
+        if !self.rmc_valid.unwrap_or(self.valid) {
+            return None;
+        }
+        let (latitude, longitude) = self.position?;
+        let accuracy = self.hdop.map_or(DEFAULT_ACCURACY, hdop_accuracy);
+        // one timebase for the whole log, the date may only come after the first fixes
+        let mut metric = LocationMetric::new(latitude, longitude, accuracy, self.time + day_offset);
+        metric.utc_time = date.map(|(year, month, day)| utc_timestamp(year, month, day, self.time));
+        if let Some(altitude) = self.altitude {
+            metric = metric.with_altitude(altitude, None);
+        }
+        if let Some(speed) = self.speed {
+            metric = metric.with_speed(speed, None);
+        }
+        if let Some(bearing) = self.bearing {
+            metric = metric.with_bearing(bearing);
+        }
+        Some(metric)
+    }
+}
+
+/// Comma separated fields of a sentence with a valid checksum, the first one is the address like `GPRMC`
+fn sentence_fields(line: &str) -> Option<Vec<&str>> {
+    let body = line.strip_prefix('$')?;
+    let (body, checksum) = match body.split_once('*') {
+        Some((body, checksum)) => (body, Some(checksum)),
+        None => (body, None),
+    };
+    if let Some(checksum) = checksum {
+        let expected = u8::from_str_radix(checksum.trim(), 16).ok()?;
+        if body.bytes().fold(0, |acc, b| acc ^ b) != expected {
+            return None;
+        }
+    }
+    let fields: Vec<&str> = body.split(',').collect();
+    (fields[0].len() == 5).then_some(fields)
+}
+
+/// `hhmmss.ss` to seconds of the day
+fn parse_time_of_day(time: &str) -> Option<f64> {
+    if time.len() < 6 {
+        return None;
+    }
+    let hours: f64 = time.get(0..2)?.parse().ok()?;
+    let minutes: f64 = time.get(2..4)?.parse().ok()?;
+    let seconds: f64 = time.get(4..)?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// `ddmmyy`, years are 2000-based
+fn parse_date(date: &str) -> Option<(i64, u32, u32)> {
+    if date.len() != 6 {
+        return None;
+    }
+    let day = date.get(0..2)?.parse().ok()?;
+    let month = date.get(2..4)?.parse().ok()?;
+    let year: i64 = date.get(4..6)?.parse().ok()?;
+    Some((2000 + year, month, day))
+}
+
+/// `ddmm.mmmm,N,dddmm.mmmm,E` starting at field `first`
+fn parse_position(fields: &[&str], first: usize) -> Option<(f64, f64)> {
+    let latitude = parse_coordinate(fields.get(first)?, fields.get(first + 1)?, 2)?;
+    let longitude = parse_coordinate(fields.get(first + 2)?, fields.get(first + 3)?, 3)?;
+    Some((latitude, longitude))
+}
+
+fn parse_coordinate(value: &str, hemisphere: &str, degree_digits: usize) -> Option<f64> {
+    let degrees: f64 = value.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = value.get(degree_digits..)?.parse().ok()?;
+    let coordinate = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(coordinate),
+        "S" | "W" => Some(-coordinate),
+        _ => None,
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use crate::tracking::{LocationEvent, LocationMetric, LocationSource, Recorder};
+use crate::tracking::geodesy::LocalProjection;
+use crate::tracking::sources::{gpx, nmea, XorShift};
+
+/// How fast recorded events are handed out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackMode {
+    /// everything is available at once, for tests
+    Instant,
+    /// events arrive with their recorded spacing
+    RealTime,
+    /// recorded time runs the given number of times faster than the wall clock
+    Accelerated(f64),
+}
+
+/// Replays a recorded location log.
+///
+/// Three formats are understood, picked by the first meaningful character of the file:
+/// - GPX track, see [`gpx::parse`]
+/// - NMEA 0183 log, see [`nmea::parse`]
+/// - text file with one fix per line: `timestamp,latitude,longitude,accuracy`.
+///   Empty lines and lines starting with `#` are ignored.
+///
+/// Noise and dropouts can be injected to check how the session copes with a worse receiver.
+pub struct ReplaySource {
+    events: VecDeque<LocationEvent>,
+    mode: PlaybackMode,
+    /// wall clock and recorded time of the playback start, set by the first poll
+    started: Option<(Instant, f64)>,
+
+    noise: f64,
+    noise_rng: XorShift,
+    dropout_rate: f64,
+    dropout_rng: XorShift,
+}
+
+impl ReplaySource {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: BufRead>(mut reader: R) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let fixes = match text.trim_start().chars().next() {
+            Some('<') => gpx::parse(text.as_bytes())?,
+            Some('$') => nmea::parse(Cursor::new(text))?,
+            _ => parse_csv(Cursor::new(text))?,
+        };
+        Ok(Self::from_fixes(fixes))
+    }
+
+    pub fn from_fixes(fixes: Vec<LocationMetric>) -> Self {
+        ReplaySource {
+            events: fixes.into_iter().map(LocationEvent::Fix).collect(),
+            mode: PlaybackMode::Instant,
+            started: None,
+
+            noise: 0.0,
+            noise_rng: XorShift::new(1),
+            dropout_rate: 0.0,
+            dropout_rng: XorShift::new(1),
+        }
+    }
+
+    pub fn mode(mut self, mode: PlaybackMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Maximal random position error in meters added to every fix
+    pub fn noise(mut self, noise: f64, seed: u64) -> Self {
+        self.noise = noise;
+        self.noise_rng = XorShift::new(seed);
+        self
+    }
+
+    /// Share of fixes in [0; 1] that are silently dropped
+    pub fn dropouts(mut self, rate: f64, seed: u64) -> Self {
+        self.dropout_rate = rate;
+        self.dropout_rng = XorShift::new(seed);
+        self
+    }
+
+    /// Drops every fix recorded between `from` and `to`, like a tunnel would
+    pub fn outage(mut self, from: f64, to: f64) -> Self {
+        self.events.retain(|event| match event {
+            LocationEvent::Fix(metric) => metric.timestamp < from || metric.timestamp > to,
+            _ => true,
+        });
+        self
+    }
+
+    /// Adds a provider event at the right place of the recording
+    pub fn provider_event(mut self, event: LocationEvent) -> Self {
+        let time = event.timestamp();
+        let index = self.events.iter().position(|e| e.timestamp() > time).unwrap_or(self.events.len());
+        self.events.insert(index, event);
+        self
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Wall clock time left until the next event is due, zero if it is due already
+    pub fn time_until_next(&self) -> Option<Duration> {
+        let next = self.events.front()?.timestamp();
+        let (start, recorded_start) = self.started?;
+        let speed = match self.mode {
+            PlaybackMode::Instant => return Some(Duration::ZERO),
+            PlaybackMode::RealTime => 1.0,
+            PlaybackMode::Accelerated(speed) => speed,
+        };
+        let due = Duration::from_secs_f64(((next - recorded_start) / speed).max(0.0));
+        Some(due.saturating_sub(start.elapsed()))
+    }
+
+    /// Plays the whole recording into `recorder` the way the location callbacks do, blocking until it ends
+    pub fn play(mut self, recorder: &Mutex<Recorder>) {
+        while !self.is_finished() {
+            recorder.lock().drain(&mut self);
+            if let Some(wait) = self.time_until_next() {
+                std::thread::sleep(wait);
+            }
+        }
+    }
+
+    fn perturb(&mut self, mut metric: LocationMetric) -> LocationMetric {
+        if self.noise > 0.0 {
+            let north = self.noise_rng.next_signed() * self.noise;
+            let east = self.noise_rng.next_signed() * self.noise;
+            let (latitude, longitude) = LocalProjection::new(metric.latitude, metric.longitude).unproject(north, east);
+            metric.latitude = latitude;
+            metric.longitude = longitude;
+        }
+        metric
+    }
+}
+
+fn parse_csv<R: BufRead>(reader: R) -> io::Result<Vec<LocationMetric>> {
+    let mut fixes = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let metric = parse_line(line).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Malformed fix at line {}: {}", i + 1, line))
+        })?;
+        fixes.push(metric);
+    }
+    Ok(fixes)
+}
+
+fn parse_line(line: &str) -> Option<LocationMetric> {
+    let mut fields = line.split(',').map(|f| f.trim().parse::<f64>());
+    let timestamp = fields.next()?.ok()?;
+    let latitude = fields.next()?.ok()?;
+    let longitude = fields.next()?.ok()?;
+    let accuracy = fields.next()?.ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(LocationMetric::new(latitude, longitude, accuracy, timestamp))
+}
+
+impl LocationSource for ReplaySource {
+    fn next_event(&mut self) -> Option<LocationEvent> {
+        loop {
+            let front = self.events.front()?.timestamp();
+            self.started.get_or_insert_with(|| (Instant::now(), front));
+            if self.time_until_next()? > Duration::ZERO {
+                return None;
+            }
+            let event = self.events.pop_front()?;
+            let LocationEvent::Fix(metric) = event else {
+                return Some(event);
+            };
+            if self.dropout_rate > 0.0 && self.dropout_rng.next_unit() < self.dropout_rate {
+                continue;
+            }
+            return Some(LocationEvent::Fix(self.perturb(metric)));
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::{TrackingConfig, TrackingSession};
+    use crate::tracking::gaps::GapReason;
+
+    const ORIGIN: (f64, f64) = (55.75, 37.61);
+
+    /// GPX of a straight walk to the north-east at `speed` m/s, one point per second
+    fn gpx_track(speed: f64, count: usize) -> String {
+        let projection = LocalProjection::new(ORIGIN.0, ORIGIN.1);
+        let mut gpx = String::from("<?xml version=\"1.0\"?>\n<gpx version=\"1.1\"><trk><trkseg>\n");
+        for i in 0..count {
+            let d = speed * i as f64 / 2f64.sqrt();
+            let (lat, lon) = projection.unproject(d, d);
+            gpx += &format!(
+                "<trkpt lat=\"{:.8}\" lon='{:.8}'><ele>{:.1}</ele><time>2024-05-01T10:{:02}:{:02}Z</time><hdop>0.8</hdop></trkpt>\n",
+                lat, lon, 150.0 + i as f64 * 0.1, i / 60, i % 60
+            );
+        }
+        gpx + "</trkseg></trk></gpx>\n"
+    }
+
+    fn nmea_sentence(body: &str) -> String {
+        let checksum = body.bytes().fold(0, |acc, b| acc ^ b);
+        format!("${}*{:02X}", body, checksum)
+    }
+
+    fn nmea_coordinate(value: f64, degree_digits: usize) -> String {
+        let degrees = value.abs().trunc();
+        format!("{:0width$}{:07.4}", degrees as u32, (value.abs() - degrees) * 60.0, width = degree_digits)
+    }
+
+    /// NMEA log of a walk to the east, GGA and RMC for every second
+    fn nmea_log(speed: f64, count: usize) -> String {
+        let projection = LocalProjection::new(ORIGIN.0, ORIGIN.1);
+        let mut log = String::new();
+        for i in 0..count {
+            let (lat, lon) = projection.unproject(0.0, speed * i as f64);
+            let time = format!("10{:02}{:02}.00", i / 60, i % 60);
+            let position = format!("{},N,{},E", nmea_coordinate(lat, 2), nmea_coordinate(lon, 3));
+            log += &nmea_sentence(&format!("GPGGA,{},{},1,09,0.9,150.0,M,14.0,M,,", time, position));
+            log += "\n";
+            log += &nmea_sentence(&format!("GNRMC,{},A,{},{:.2},90.0,010524,,,A", time, position, speed / KNOTS_TO_MS));
+            log += "\n";
+            log += &nmea_sentence("GPGSV,3,1,11,10,63,137,17,07,61,098,15,05,59,290,20,08,54,157,30");
+            log += "\n";
+        }
+        log
+    }
+
+    const KNOTS_TO_MS: f64 = 1852.0 / 3600.0;
+
+    fn config() -> TrackingConfig {
+        TrackingConfig {
+            smoothing: false,
+            ..TrackingConfig::default()
+        }
+    }
+
+    fn replay(source: &mut ReplaySource) -> TrackingSession {
+        let mut recorder = Recorder::new();
+        recorder.start(config());
+        recorder.drain(source);
+        recorder.finish()
+    }
+
+    #[test]
+    fn gpx_walk() {
+        let mut source = ReplaySource::from_reader(gpx_track(1.5, 600).as_bytes()).unwrap();
+        assert_eq!(source.remaining(), 600);
+        let session = replay(&mut source);
+
+        // warm-up drops the first 10 fixes
+        assert!((session.total_distance() - 589.0 * 1.5).abs() < 2.0, "{}", session.total_distance());
+        assert!((session.total_time() - 589.0).abs() < 1e-6);
+        assert!((session.elevation_gain() - 58.9).abs() < 3.1);
+        let first = session.raw_track().next().unwrap();
+        assert_eq!(first.accuracy, 4.0);
+        assert_eq!(first.timestamp, utc_seconds("2024-05-01T10:00:10Z"));
+    }
+
+    fn utc_seconds(time: &str) -> f64 {
+        let gpx = format!("<trkpt lat=\"0\" lon=\"0\"><time>{}</time></trkpt>", time);
+        gpx::parse(gpx.as_bytes()).unwrap()[0].timestamp
+    }
+
+    #[test]
+    fn gpx_time_zones() {
+        assert_eq!(utc_seconds("1970-01-01T00:00:00Z"), 0.0);
+        assert_eq!(utc_seconds("2000-03-01T00:00:00Z"), 951868800.0);
+        assert_eq!(utc_seconds("2024-05-01T12:00:00.5+02:00"), utc_seconds("2024-05-01T10:00:00.5Z"));
+    }
+
+    #[test]
+    fn gpx_points_without_time() {
+        let gpx = "<trkpt lat=\"55.75\" lon=\"37.61\"/><trkpt lat=\"55.7501\" lon=\"37.61\"></trkpt>";
+        let points = gpx::parse(gpx.as_bytes()).unwrap();
+        assert_eq!((points[0].timestamp, points[1].timestamp), (0.0, 1.0));
+        assert!(points.iter().all(|point| point.utc_time.is_none()));
+
+        // multibyte characters where quotes are expected
+        for gpx in ["<trkpt lat=«55» lon=\"37\"/>", "<trkpt lat=\"55\" lon=\u{fe}37\u{fe}/>", "<trkpt lat=é"] {
+            assert!(gpx::parse(gpx.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn nmea_walk() {
+        let log = nmea_log(2.0, 300) + "$GPGGA,corrupted*00\n";
+        let mut source = ReplaySource::from_reader(log.as_bytes()).unwrap();
+        assert_eq!(source.remaining(), 300);
+        let session = replay(&mut source);
+
+        // coordinates are rounded to 0.0001 minutes, about 20 cm
+        assert!((session.total_distance() - 289.0 * 2.0).abs() < 2.0, "{}", session.total_distance());
+        let fix = session.raw_track().next().unwrap();
+        assert!((fix.accuracy - 4.5).abs() < 1e-9);
+        assert_eq!(fix.altitude, Some(164.0));
+        assert!((fix.speed.unwrap() - 2.0).abs() < 0.01);
+        assert_eq!(fix.bearing, Some(90.0));
+    }
+
+    #[test]
+    fn nmea_invalid_fixes_are_skipped() {
+        let log = [
+            nmea_sentence("GPRMC,100000.00,V,,,,,,,010524,,,N"),
+            nmea_sentence("GPGGA,100000.00,,,,,0,00,99.9,,,,,,"),
+            nmea_sentence("GPGGA,100001.00,5545.0000,N,03736.6000,E,1,08,1.0,150.0,M,14.0,M,,"),
+        ].join("\n");
+        let fixes = nmea::parse(log.as_bytes()).unwrap();
+        assert_eq!(fixes.len(), 1);
+        assert!((fixes[0].latitude - 55.75).abs() < 1e-9);
+        assert!((fixes[0].longitude - 37.61).abs() < 1e-9);
+        assert_eq!(fixes[0].timestamp, 36001.0);
+        assert_eq!(fixes[0].utc_time, Some(utc_seconds("2024-05-01T10:00:01Z")));
+    }
+
+    #[test]
+    fn nmea_date_arrives_late() {
+        // GGA only until the receiver starts sending RMC with the date
+        let mut log = String::new();
+        for i in 0..40 {
+            // from 23:59:30
+            let seconds = (86370 + i) % 86400;
+            let time = format!("{:02}{:02}{:02}.00", seconds / 3600, seconds / 60 % 60, seconds % 60);
+            let position = format!("5545.{:04},N,03736.6000,E", i * 10);
+            log += &nmea_sentence(&format!("GPGGA,{},{},1,08,1.0,150.0,M,14.0,M,,", time, position));
+            log += "\n";
+            if i >= 20 {
+                let date = if i < 30 { "300424" } else { "010524" };
+                log += &nmea_sentence(&format!("GPRMC,{},A,{},0.0,0.0,{},,,A", time, position, date));
+                log += "\n";
+            }
+        }
+        let fixes = nmea::parse(log.as_bytes()).unwrap();
+        assert_eq!(fixes.len(), 40);
+        // seconds across midnight, no jump when the date shows up
+        for (i, fix) in fixes.iter().enumerate() {
+            assert_eq!(fix.timestamp, 86370.0 + i as f64);
+            assert_eq!(fix.utc_time.is_some(), i >= 20);
+        }
+        assert_eq!(fixes[30].utc_time, Some(utc_seconds("2024-05-01T00:00:00Z")));
+
+        let session = replay(&mut ReplaySource::from_fixes(fixes));
+        assert!(session.gaps().is_empty());
+        assert_eq!(session.total_time(), 29.0);
+        assert_eq!(session.start_utc(), Some(utc_seconds("2024-04-30T23:59:40Z")));
+    }
+
+    #[test]
+    fn noise_and_dropouts() {
+        let clean = replay(&mut ReplaySource::from_reader(gpx_track(1.5, 600).as_bytes()).unwrap());
+        let mut source = ReplaySource::from_reader(gpx_track(1.5, 600).as_bytes()).unwrap()
+            .noise(1.0, 7)
+            .dropouts(0.2, 3);
+        let noisy = replay(&mut source);
+
+        let accepted = noisy.raw_track().count();
+        assert!(accepted > 400 && accepted < 520, "{}", accepted);
+        // jitter makes the track longer, but not by much
+        assert!(noisy.total_distance() > clean.total_distance() * 0.95);
+        assert!(noisy.total_distance() < clean.total_distance() * 1.6, "{}", noisy.total_distance());
+    }
+
+    #[test]
+    fn outage_produces_gap() {
+        let start = utc_seconds("2024-05-01T10:00:00Z");
+        let mut source = ReplaySource::from_reader(gpx_track(1.5, 300).as_bytes()).unwrap()
+            .outage(start + 100.5, start + 129.5)
+            .provider_event(LocationEvent::ProviderDisabled(start + 100.5));
+        let session = replay(&mut source);
+
+        assert_eq!(session.gaps().len(), 1);
+        let gap = &session.gaps()[0];
+        // fixes after the provider comes back go through the warm-up again
+        assert_eq!((gap.start - start, gap.end - start), (100.0, 140.0));
+        assert_eq!(gap.reason, GapReason::ProviderDisabled);
+    }
+
+    #[test]
+    fn timed_playback() {
+        let fixes = (0..5).map(|i| LocationMetric::new(ORIGIN.0, ORIGIN.1, 3.0, i as f64)).collect();
+        let mut source = ReplaySource::from_fixes(fixes).mode(PlaybackMode::RealTime);
+        assert!(source.next_event().is_some());
+        assert!(source.next_event().is_none());
+        assert!(source.time_until_next().unwrap() > Duration::from_millis(900));
+
+        let fixes = (0..5).map(|i| LocationMetric::new(ORIGIN.0, ORIGIN.1, 3.0, i as f64)).collect();
+        let source = ReplaySource::from_fixes(fixes).mode(PlaybackMode::Accelerated(100.0));
+        let recorder = Mutex::new(Recorder::new());
+        recorder.lock().start(config());
+        let start = Instant::now();
+        source.play(&recorder);
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(recorder.lock().session().diagnostics().warm_up, 5);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::tracking::{LocationEvent, LocationMetric, LocationSource};
+use crate::tracking::geodesy::LocalProjection;
+use crate::tracking::sources::XorShift;
+
+/// Generates fixes of a constant-speed movement along a fixed heading.
+///
+/// Jitter is produced by a seeded xorshift generator, so the same parameters always give the same track.
+pub struct SyntheticSource {
+    origin: (f64, f64),
+    /// meters per second
+    speed: f64,
+    /// degrees clockwise from north
+    heading: f64,
+    interval: f64,
+    accuracy: f64,
+    jitter: f64,
+
+    start_time: f64,
+    emitted: usize,
+    count: usize,
+    rng: XorShift,
+}
+
+impl SyntheticSource {
+    pub fn new(origin: (f64, f64), speed: f64, heading: f64, count: usize) -> Self {
+        SyntheticSource {
+            origin,
+            speed,
+            heading,
+            interval: 1.0,
+            accuracy: 3.0,
+            jitter: 0.0,
+
+            start_time: 0.0,
+            emitted: 0,
+            count,
+            rng: XorShift::new(0x2545_F491_4F6C_DD1D),
+        }
+    }
+
+    /// Seconds between two consecutive fixes
+    pub fn interval(mut self, interval: f64) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Reported accuracy of every fix
+    pub fn accuracy(mut self, accuracy: f64) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    /// Maximal random position error in meters
+    pub fn jitter(mut self, jitter: f64, seed: u64) -> Self {
+        self.jitter = jitter;
+        self.rng = XorShift::new(seed);
+        self
+    }
+
+    pub fn start_time(mut self, start_time: f64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+}
+
+impl LocationSource for SyntheticSource {
+    fn next_event(&mut self) -> Option<LocationEvent> {
+        if self.emitted >= self.count {
+            return None;
+        }
+
+        let t = self.emitted as f64 * self.interval;
+        let heading = self.heading.to_radians();
+        let mut north = self.speed * t * heading.cos();
+        let mut east = self.speed * t * heading.sin();
+        if self.jitter > 0.0 {
+            north += self.rng.next_signed() * self.jitter;
+            east += self.rng.next_signed() * self.jitter;
+        }
+
+        let (latitude, longitude) = LocalProjection::new(self.origin.0, self.origin.1).unproject(north, east);
+        self.emitted += 1;
+
+        Some(LocationEvent::Fix(LocationMetric::new(latitude, longitude, self.accuracy, self.start_time + t)))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.emitted >= self.count
+    }
+}
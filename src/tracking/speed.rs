@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+/// Default length of the rolling window, seconds
+pub const DEFAULT_SPEED_WINDOW: f64 = 10.0;
+/// Weight of a new window speed in the smoothed value
+const SMOOTHING: f64 = 0.3;
+
+/// Instantaneous speed from the distance covered over a rolling time window, exponentially smoothed
+#[derive(Clone, Debug)]
+pub struct SpeedEstimator {
+    window_len: f64,
+    /// (timestamp, cumulative distance)
+    window: VecDeque<(f64, f64)>,
+    smoothed: Option<f64>,
+}
+
+impl SpeedEstimator {
+    pub fn new(window_len: f64) -> Self {
+        SpeedEstimator {
+            window_len,
+            window: VecDeque::new(),
+            smoothed: None,
+        }
+    }
+
+    /// Feeds the cumulative distance at an accepted fix.
+    ///
+    /// `accuracy` is the horizontal accuracy of the fix, `doppler` is the device reported (speed, speed accuracy).
+    /// Doppler speed replaces the window speed when it is more accurate than position differencing.
+    pub fn update(&mut self, timestamp: f64, distance: f64, accuracy: f64, doppler: Option<(f64, f64)>) {
+        self.window.push_back((timestamp, distance));
+        while let Some(&(t, _)) = self.window.front() {
+            if timestamp - t > self.window_len && self.window.len() > 2 {
+                self.window.pop_front();
+            }
+            else {
+                break;
+            }
+        }
+
+        let (t0, d0) = *self.window.front().unwrap();
+        let span = timestamp - t0;
+        let window_speed = (span > 0.0).then(|| ((distance - d0) / span, accuracy * std::f64::consts::SQRT_2 / span));
+
+        let speed = match (window_speed, doppler) {
+            (Some((_, window_acc)), Some((speed, speed_acc))) if speed_acc < window_acc => speed,
+            (None, Some((speed, _))) => speed,
+            (Some((speed, _)), _) => speed,
+            (None, None) => return,
+        };
+        self.smoothed = Some(match self.smoothed {
+            // no movement over the whole window is a stop, not something to ease into
+            Some(_) if speed == 0.0 && span >= self.window_len => 0.0,
+            Some(prev) => prev + (speed - prev) * SMOOTHING,
+            None => speed,
+        });
+    }
+
+    /// m/s, 0 until at least two fixes are in the window and once a whole window passes without movement
+    pub fn speed(&self) -> f64 {
+        self.smoothed.unwrap_or(0.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.smoothed = None;
+    }
+}
+
+impl Default for SpeedEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SPEED_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_speed() {
+        let mut estimator = SpeedEstimator::default();
+        assert_eq!(estimator.speed(), 0.0);
+        for t in 0..30 {
+            estimator.update(t as f64, t as f64 * 2.5, 3.0, None);
+        }
+        assert!((estimator.speed() - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stop_reaches_zero_within_the_window() {
+        let mut estimator = SpeedEstimator::new(10.0);
+        for t in 0..=20 {
+            estimator.update(t as f64, t as f64 * 2.0, 3.0, None);
+        }
+        let mut speeds = Vec::new();
+        for t in 21..=30 {
+            estimator.update(t as f64, 40.0, 3.0, None);
+            speeds.push(estimator.speed());
+        }
+        assert!(speeds.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", speeds);
+        assert!(speeds[8] > 0.0);
+        assert_eq!(speeds[9], 0.0);
+    }
+
+    #[test]
+    fn reset_clears_the_window() {
+        let mut estimator = SpeedEstimator::default();
+        for t in 0..10 {
+            estimator.update(t as f64, t as f64 * 3.0, 3.0, None);
+        }
+        estimator.reset();
+        assert_eq!(estimator.speed(), 0.0);
+        // a single fix after the reset gives no speed, the old distance is gone
+        estimator.update(20.0, 500.0, 3.0, None);
+        assert_eq!(estimator.speed(), 0.0);
+        estimator.update(21.0, 501.0, 3.0, None);
+        assert!((estimator.speed() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn doppler_used_when_more_accurate() {
+        // 2 m/s by position, the last fix spans a 10 s window of 3 m fixes, about 0.42 m/s accurate
+        let feed = |doppler: Option<(f64, f64)>| {
+            let mut estimator = SpeedEstimator::default();
+            for t in 0..=10 {
+                estimator.update(t as f64, t as f64 * 2.0, 3.0, None);
+            }
+            estimator.update(11.0, 22.0, 3.0, doppler);
+            estimator.speed()
+        };
+        assert!((feed(None) - 2.0).abs() < 1e-9);
+        // smoothing moves 30% of the way to the doppler speed
+        assert!((feed(Some((3.0, 0.1))) - 2.3).abs() < 1e-9);
+        assert!((feed(Some((3.0, 1.0))) - 2.0).abs() < 1e-9);
+
+        // a single fix has no window speed, doppler is all there is
+        let mut estimator = SpeedEstimator::default();
+        estimator.update(0.0, 0.0, 3.0, Some((1.3, 2.0)));
+        assert_eq!(estimator.speed(), 1.3);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::tracking::LocationMetric;
+
+/// Continuous part of a session between start (or resume) and the following pause.
+///
+/// Distance and time are only accumulated between fixes of the same segment,
+/// so the paused gap between two segments counts toward neither.
+#[derive(Clone, Debug, Default)]
+pub struct TrackSegment {
+    /// accepted fixes exactly as reported by the source
+    pub raw: Vec<LocationMetric>,
+    /// accepted fixes after smoothing, empty if smoothing is disabled.
+    /// Toggling smoothing closes the segment, so this is either empty or as long as `raw`.
+    pub filtered: Vec<LocationMetric>,
+    /// accepted fixes as (north, east) offsets in meters from the session origin, for display only
+    pub display: Vec<LocationMetric>,
+    pub distance: f64,
+    pub time: f64,
+}
+
+impl TrackSegment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fixes used for distance accumulation: smoothed if available, raw otherwise
+    pub fn points(&self) -> &[LocationMetric] {
+        if self.filtered.is_empty() {
+            &self.raw
+        }
+        else {
+            &self.filtered
+        }
+    }
+
+    pub fn start_time(&self) -> Option<f64> {
+        self.raw.first().map(|m| m.timestamp)
+    }
+
+    pub fn end_time(&self) -> Option<f64> {
+        self.raw.last().map(|m| m.timestamp)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+}
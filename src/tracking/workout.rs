@@ -0,0 +1,319 @@
+use std::fmt;
+use crate::tracking::SessionEvent;
+use crate::tracking::laps::{format_duration, format_pace};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StepKind {
+    WarmUp,
+    Work,
+    Recovery,
+    CoolDown,
+}
+
+impl fmt::Display for StepKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StepKind::WarmUp => "Warm-up",
+            StepKind::Work => "Work",
+            StepKind::Recovery => "Recovery",
+            StepKind::CoolDown => "Cool-down",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Amount of a step, training time excludes pauses
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum StepTarget {
+    /// meters
+    Distance(f64),
+    /// seconds
+    Duration(f64),
+}
+
+/// Accepted pace range, seconds per km
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PaceBand {
+    pub fastest: f64,
+    pub slowest: f64,
+}
+
+impl PaceBand {
+    pub fn check(&self, pace: f64) -> PaceCheck {
+        if pace < self.fastest {
+            PaceCheck::TooFast
+        } else if pace > self.slowest {
+            PaceCheck::TooSlow
+        } else {
+            PaceCheck::InBand
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaceCheck {
+    TooFast,
+    InBand,
+    TooSlow,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkoutStep {
+    pub kind: StepKind,
+    pub target: StepTarget,
+    pub pace: Option<PaceBand>,
+}
+
+impl WorkoutStep {
+    pub fn new(kind: StepKind, target: StepTarget) -> Self {
+        WorkoutStep {
+            kind,
+            target,
+            pace: None,
+        }
+    }
+
+    /// Pace band in seconds per km
+    pub fn pace(mut self, fastest: f64, slowest: f64) -> Self {
+        self.pace = Some(PaceBand { fastest, slowest });
+        self
+    }
+}
+
+/// Steps of a structured workout, repeats are stored unrolled
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkoutPlan {
+    pub name: String,
+    pub steps: Vec<WorkoutStep>,
+}
+
+impl WorkoutPlan {
+    pub fn new(name: &str) -> Self {
+        WorkoutPlan {
+            name: name.to_string(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn step(mut self, step: WorkoutStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Appends `steps` `count` times
+    pub fn repeat(mut self, count: usize, steps: &[WorkoutStep]) -> Self {
+        for _ in 0..count {
+            self.steps.extend_from_slice(steps);
+        }
+        self
+    }
+
+    /// Warm-up, 6 x (400 m fast / 200 m easy), cool-down
+    pub fn track_intervals() -> Self {
+        WorkoutPlan::new("6x400m intervals")
+            .step(WorkoutStep::new(StepKind::WarmUp, StepTarget::Duration(600.0)))
+            .repeat(6, &[
+                WorkoutStep::new(StepKind::Work, StepTarget::Distance(400.0)).pace(210.0, 250.0),
+                WorkoutStep::new(StepKind::Recovery, StepTarget::Distance(200.0)).pace(300.0, 480.0),
+            ])
+            .step(WorkoutStep::new(StepKind::CoolDown, StepTarget::Duration(600.0)))
+    }
+}
+
+/// Outcome of a finished step, stored with the record
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StepResult {
+    pub index: usize,
+    pub kind: StepKind,
+    /// meters
+    pub distance: f64,
+    /// seconds
+    pub duration: f64,
+    /// seconds per km
+    pub pace: f64,
+    /// step had a pace band and its average pace was inside it
+    pub in_band: Option<bool>,
+}
+
+/// Step in progress as shown on the active screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepProgress {
+    pub index: usize,
+    pub count: usize,
+    pub step: WorkoutStep,
+    /// meters or seconds, same unit as the target
+    pub remaining: f64,
+}
+
+impl fmt::Display for StepProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Step {}/{} {}: ", self.index + 1, self.count, self.step.kind)?;
+        match self.step.target {
+            StepTarget::Distance(_) => write!(f, "{:.0}m left", self.remaining)?,
+            StepTarget::Duration(_) => write!(f, "{} left", format_duration(self.remaining))?,
+        }
+        if let Some(band) = self.step.pace {
+            write!(f, ", {}-{}/km", format_pace(band.fastest), format_pace(band.slowest))?;
+        }
+        Ok(())
+    }
+}
+
+/// State machine walking through the steps of a plan as the session totals grow
+#[derive(Clone, Debug)]
+pub struct WorkoutTracker {
+    plan: WorkoutPlan,
+    current: usize,
+    /// (distance, time) at the start of the current step
+    step_start: (f64, f64),
+    /// (distance, time) of the previous update, to split a fix crossing a step boundary
+    previous: (f64, f64),
+    results: Vec<StepResult>,
+}
+
+impl WorkoutTracker {
+    pub fn new(plan: WorkoutPlan) -> Self {
+        WorkoutTracker {
+            plan,
+            current: 0,
+            step_start: (0.0, 0.0),
+            previous: (0.0, 0.0),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn plan(&self) -> &WorkoutPlan {
+        &self.plan
+    }
+
+    pub fn results(&self) -> &[StepResult] {
+        &self.results
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.plan.steps.len()
+    }
+
+    /// Takes the session totals after an accepted fix, finishes every step whose target was covered
+    pub fn update(&mut self, distance: f64, time: f64, events: &mut Vec<SessionEvent>) {
+        let (prev_distance, prev_time) = self.previous;
+        self.previous = (distance, time);
+
+        while let Some(step) = self.plan.steps.get(self.current) {
+            let (start_distance, start_time) = self.step_start;
+            // (distance, time) where the step ends, interpolated inside the last fix step
+            let end = match step.target {
+                StepTarget::Distance(target) if distance - start_distance >= target => {
+                    let end_distance = start_distance + target;
+                    let f = fraction(prev_distance, distance, end_distance);
+                    (end_distance, prev_time + (time - prev_time) * f)
+                }
+                StepTarget::Duration(target) if time - start_time >= target => {
+                    let end_time = start_time + target;
+                    let f = fraction(prev_time, time, end_time);
+                    (prev_distance + (distance - prev_distance) * f, end_time)
+                }
+                _ => break,
+            };
+            self.finish_step(end, events);
+        }
+    }
+
+    fn finish_step(&mut self, (end_distance, end_time): (f64, f64), events: &mut Vec<SessionEvent>) {
+        let step = &self.plan.steps[self.current];
+        let (start_distance, start_time) = self.step_start;
+        let distance = end_distance - start_distance;
+        let duration = end_time - start_time;
+        let pace = if distance > 0.0 { duration / (distance / 1000.0) } else { 0.0 };
+        let result = StepResult {
+            index: self.current,
+            kind: step.kind,
+            distance,
+            duration,
+            pace,
+            in_band: step.pace.map(|band| distance > 0.0 && band.check(pace) == PaceCheck::InBand),
+        };
+        events.push(SessionEvent::StepFinished(result.clone()));
+        self.results.push(result);
+        self.current += 1;
+        self.step_start = (end_distance, end_time);
+    }
+
+    pub fn progress(&self, distance: f64, time: f64) -> Option<StepProgress> {
+        let step = self.plan.steps.get(self.current)?;
+        let (start_distance, start_time) = self.step_start;
+        let remaining = match step.target {
+            StepTarget::Distance(target) => target - (distance - start_distance),
+            StepTarget::Duration(target) => target - (time - start_time),
+        };
+        Some(StepProgress {
+            index: self.current,
+            count: self.plan.steps.len(),
+            step: step.clone(),
+            remaining: remaining.max(0.0),
+        })
+    }
+}
+
+/// Where `value` lies between `from` and `to`, in [0; 1]
+fn fraction(from: f64, to: f64, value: f64) -> f64 {
+    if to > from { ((value - from) / (to - from)).clamp(0.0, 1.0) } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(tracker: &mut WorkoutTracker, speed: f64, seconds: usize, start: (f64, f64), events: &mut Vec<SessionEvent>) -> (f64, f64) {
+        let (mut distance, mut time) = start;
+        for _ in 0..seconds {
+            distance += speed;
+            time += 1.0;
+            tracker.update(distance, time, events);
+        }
+        (distance, time)
+    }
+
+    #[test]
+    fn intervals_advance_through_steps() {
+        let mut tracker = WorkoutTracker::new(WorkoutPlan::track_intervals());
+        let mut events = Vec::new();
+
+        // 10 min warm-up at 2.5 m/s
+        let at = feed(&mut tracker, 2.5, 600, (0.0, 0.0), &mut events);
+        assert_eq!(tracker.results().len(), 1);
+        let progress = tracker.progress(at.0, at.1).unwrap();
+        assert_eq!((progress.index, progress.step.kind, progress.remaining), (1, StepKind::Work, 400.0));
+
+        // 400 m at 4 m/s is a 4:10/km pace, 200 m at 2.5 m/s is 6:40/km
+        let mut at = at;
+        for _ in 0..6 {
+            at = feed(&mut tracker, 4.0, 100, at, &mut events);
+            at = feed(&mut tracker, 2.5, 80, at, &mut events);
+        }
+        assert_eq!(tracker.results().len(), 13);
+        assert!(tracker.results()[1..].iter().all(|r| r.in_band == Some(true)), "{:?}", tracker.results());
+        assert!((tracker.results()[1].pace - 250.0).abs() < 1e-6);
+
+        feed(&mut tracker, 2.0, 650, at, &mut events);
+        assert!(tracker.is_finished());
+        assert_eq!(events.len(), 14);
+        assert!(tracker.progress(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn fix_crossing_several_steps() {
+        let plan = WorkoutPlan::new("short")
+            .repeat(3, &[WorkoutStep::new(StepKind::Work, StepTarget::Distance(100.0)).pace(150.0, 200.0)]);
+        let mut tracker = WorkoutTracker::new(plan);
+        let mut events = Vec::new();
+
+        // a long jump after a signal loss covers two and a half steps
+        tracker.update(250.0, 60.0, &mut events);
+        assert_eq!(tracker.results().len(), 2);
+        assert_eq!(tracker.results()[1].distance, 100.0);
+        assert!((tracker.results()[1].duration - 24.0).abs() < 1e-9);
+        assert_eq!(tracker.results()[1].in_band, Some(false));
+        assert_eq!(tracker.progress(250.0, 60.0).unwrap().remaining, 50.0);
+    }
+}